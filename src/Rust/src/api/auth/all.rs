@@ -0,0 +1,36 @@
+use crate::{config, database};
+
+use super::super::Error;
+use actix_web::{HttpResponse, Responder};
+use serde_json::json;
+
+/// 不返回密钥本身
+pub async fn all() -> Result<impl Responder, Error> {
+    let mut keys: Vec<_> = config::current()
+        .auth
+        .keys
+        .iter()
+        .map(|key| {
+            json!({
+                "name": key.name,
+                "scope": key.scope,
+                "instances": key.instances,
+                "created": null,
+                "source": "config",
+            })
+        })
+        .collect();
+    for key in database::auth::all().map_err(|_| Error::Internal)? {
+        keys.push(json!({
+            "name": key.name,
+            "scope": key.scope,
+            "instances": key.instances,
+            "created": key.created,
+            "source": "api",
+        }));
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "keys": keys,
+        "result":  0,
+    })))
+}
@@ -0,0 +1,39 @@
+use crate::{auth, config::{self, Scope}, database::{self, auth::StoredKey}};
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+    scope: Scope,
+    instances: Option<Vec<i64>>,
+}
+
+/// 生成的密钥只在这里返回一次
+pub async fn create(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let req = req.into_inner();
+    let stored = database::auth::all().map_err(|_| Error::Internal)?;
+    if req.name.is_empty()
+        || config::current().auth.keys.iter().any(|x| x.name == req.name)
+        || stored.iter().any(|x| x.name == req.name)
+    {
+        return Err(Error::InvalidRequest);
+    }
+    let key = auth::generate();
+    database::auth::save(&StoredKey {
+        name: req.name,
+        hash: auth::hash(&key),
+        scope: req.scope,
+        instances: req.instances,
+        created: chrono::Local::now().timestamp_millis(),
+    })
+    .map_err(|_| Error::Internal)?;
+    auth::invalidate();
+    Ok(HttpResponse::Ok().json(json!({
+        "key": key,
+        "result":  0,
+    })))
+}
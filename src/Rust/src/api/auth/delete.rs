@@ -0,0 +1,22 @@
+use crate::{auth, database};
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+}
+
+/// 配置文件中的密钥不能删除
+pub async fn delete(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    if !database::auth::remove(&req.name).map_err(|_| Error::Internal)? {
+        return Err(Error::KeyNotFound);
+    }
+    auth::invalidate();
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
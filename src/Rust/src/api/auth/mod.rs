@@ -0,0 +1,9 @@
+use actix_web::web;
+mod create;
+mod all;
+mod delete;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/auth/key/create", web::post().to(create::create));
+    cfg.route("/auth/key/all", web::post().to(all::all));
+    cfg.route("/auth/key/delete", web::post().to(delete::delete));
+}
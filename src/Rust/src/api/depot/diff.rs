@@ -0,0 +1,31 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    uuid: String,
+    from: u64,
+    to: Option<u64>,
+}
+
+pub async fn diff(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let from = database::depot::get(&req.uuid, req.from)
+        .map_err(|_| Error::Internal)?
+        .ok_or(Error::SnapshotNotFound)?;
+    let to = match req.to {
+        Some(to) => database::depot::get(&req.uuid, to),
+        None => database::depot::latest(&req.uuid),
+    }
+    .map_err(|_| Error::Internal)?
+    .ok_or(Error::SnapshotNotFound)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "from": {"id": from.id, "time": from.time},
+        "to": {"id": to.id, "time": to.time},
+        "diff": database::depot::diff(&from.data, &to.data),
+        "result":  0,
+    })))
+}
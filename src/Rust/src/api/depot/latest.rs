@@ -0,0 +1,26 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    uuid: String,
+}
+
+pub async fn latest(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let snapshot = database::depot::latest(&req.uuid)
+        .map_err(|_| Error::Internal)?
+        .ok_or(Error::SnapshotNotFound)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "snapshot": {
+            "id": snapshot.id,
+            "time": snapshot.time,
+            "instance": snapshot.instance,
+            "items": super::items(&snapshot.data),
+        },
+        "result":  0,
+    })))
+}
@@ -0,0 +1,32 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct Req {
+    uuid: String,
+    nums: Option<i64>,
+}
+
+pub async fn list(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let nums = req.nums.unwrap_or(i64::MAX);
+    let snapshots = database::depot::list(&req.uuid, nums as usize).map_err(|_| Error::Internal)?;
+    let ret: Vec<Value> = snapshots
+        .iter()
+        .map(|x| {
+            json!({
+                "id": x.id,
+                "time": x.time,
+                "instance": x.instance,
+                "kinds": x.data.len(),
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({
+        "snapshots": ret,
+        "result":  0,
+    })))
+}
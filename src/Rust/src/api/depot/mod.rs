@@ -0,0 +1,24 @@
+use actix_web::web;
+use serde_json::{json, Value};
+use crate::{database::depot::Depot, resource};
+mod latest;
+mod list;
+mod diff;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/depot/latest", web::post().to(latest::latest));
+    cfg.route("/depot/list", web::post().to(list::list));
+    cfg.route("/depot/diff", web::post().to(diff::diff));
+}
+
+fn items(depot: &Depot) -> Vec<Value> {
+    depot
+        .iter()
+        .map(|(id, have)| {
+            json!({
+                "id": id,
+                "name": resource::item_name(id),
+                "have": have,
+            })
+        })
+        .collect()
+}
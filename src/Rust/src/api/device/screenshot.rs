@@ -1,4 +1,5 @@
 use std::sync::Mutex;
+use crate::metrics;
 use super::super::{Error, MaaManager};
 use actix_web::{HttpResponse, Responder, web};
 use serde::Deserialize;
@@ -11,6 +12,8 @@ pub struct Req {
 pub async fn screenshot(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
     let manager = maa_manager.lock().map_err(|_|Error::Internal)?;
     let maa = manager.get(req.id).ok_or(Error::InstanceNotFound)?;
+    let start = std::time::Instant::now();
     let body = maa.screenshot()?;
+    metrics::observe_screenshot("api", start.elapsed());
     Ok(HttpResponse::Ok().body(body))
 }
\ No newline at end of file
@@ -0,0 +1,21 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: Option<i64>,
+    nums: Option<i64>,
+}
+
+pub async fn all(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let nums = req.nums.unwrap_or(i64::MAX);
+    let flows = database::flow::list(req.id, nums as usize).map_err(|_| Error::Internal)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "flows": flows,
+        "result":  0,
+    })))
+}
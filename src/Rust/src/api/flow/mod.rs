@@ -0,0 +1,11 @@
+use actix_web::web;
+mod start;
+mod stop;
+mod get;
+mod all;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/flow/start", web::post().to(start::start));
+    cfg.route("/flow/stop", web::post().to(stop::stop));
+    cfg.route("/flow/get", web::post().to(get::get));
+    cfg.route("/flow/all", web::post().to(all::all));
+}
@@ -0,0 +1,22 @@
+use std::sync::Mutex;
+use crate::{database::flow::StepSpec, flow};
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+    steps: Vec<StepSpec>,
+}
+
+pub async fn start(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let req = req.into_inner();
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    let flow_id = flow::create(&mut manager, req.id, req.steps)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+        "flow_id": flow_id
+    })))
+}
@@ -0,0 +1,19 @@
+use std::sync::Mutex;
+use crate::flow;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    flow_id: u64,
+}
+
+pub async fn stop(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    flow::stop(&mut manager, req.flow_id, "flow stopped by request")?;
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
@@ -0,0 +1,12 @@
+use std::sync::Mutex;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde_json::json;
+
+pub async fn all(maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "groups": manager.get_all_groups(),
+        "result":  0,
+    })))
+}
@@ -0,0 +1,22 @@
+use std::sync::Mutex;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+}
+
+pub async fn back_to_home(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    let results = super::fan_out(&mut manager, &req.name, |manager, id| {
+        manager.get(id).ok_or(Error::InstanceNotFound)?.back_to_home()?;
+        Ok(json!({ "result": 0 }))
+    })?;
+    Ok(HttpResponse::Ok().json(json!({
+        "results": results,
+        "result":  0,
+    })))
+}
@@ -0,0 +1,19 @@
+use std::sync::Mutex;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+}
+
+/// 只删除分组，不影响组内的实例
+pub async fn delete(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    manager.delete_group(&req.name).ok_or(Error::GroupNotFound)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
@@ -0,0 +1,44 @@
+use actix_web::web;
+use serde_json::{json, Value};
+use super::{Error, MaaManager};
+mod set;
+mod delete;
+mod all;
+mod task;
+mod start;
+mod stop;
+mod back_to_home;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/group/set", web::post().to(set::set));
+    cfg.route("/group/delete", web::post().to(delete::delete));
+    cfg.route("/group/all", web::post().to(all::all));
+    cfg.route("/group/task/create", web::post().to(task::create));
+    cfg.route("/group/start", web::post().to(start::start));
+    cfg.route("/group/stop", web::post().to(stop::stop));
+    cfg.route("/group/back_to_home", web::post().to(back_to_home::back_to_home));
+}
+
+/// 对组内每个实例执行f，某个实例失败时继续处理其余实例
+fn fan_out<F>(manager: &mut MaaManager, name: &str, mut f: F) -> Result<Vec<Value>, Error>
+where
+    F: FnMut(&mut MaaManager, i64) -> Result<Value, Error>,
+{
+    let ids = manager.get_group(name).ok_or(Error::GroupNotFound)?;
+    let results = ids
+        .into_iter()
+        .map(|id| {
+            let mut result = match f(manager, id) {
+                Ok(Value::Object(result)) => result,
+                Ok(_) => serde_json::Map::new(),
+                Err(e) => {
+                    let mut result = serde_json::Map::new();
+                    result.insert("error".to_string(), json!(e.message()));
+                    result
+                }
+            };
+            result.insert("id".to_string(), json!(id));
+            Value::Object(result)
+        })
+        .collect();
+    Ok(results)
+}
@@ -0,0 +1,36 @@
+use std::sync::Mutex;
+use crate::metrics;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+    types: String,
+    params: Value,
+}
+
+pub async fn create(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let params = match req.params {
+        Value::Null => json!({}),
+        Value::Object(_) => req.params.clone(),
+        _=>return Err(Error::InvalidRequest)
+    };
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    let results = super::fan_out(&mut manager, &req.name, |manager, id| {
+        manager.get(id).ok_or(Error::InstanceNotFound)?;
+        if let Some(pending_id) = manager.enqueue(id, &req.types, params.clone()) {
+            return Ok(json!({ "result": 0, "task_id": null, "pending_id": pending_id }));
+        }
+        let maa = manager.get_mut(id).ok_or(Error::InstanceNotFound)?;
+        let task_id = maa.create_task(&req.types, &params.to_string())?;
+        metrics::task_appended(&req.types);
+        Ok(json!({ "result": 0, "task_id": task_id }))
+    })?;
+    Ok(HttpResponse::Ok().json(json!({
+        "results": results,
+        "result":  0,
+    })))
+}
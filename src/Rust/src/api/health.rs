@@ -0,0 +1,36 @@
+use std::sync::Mutex;
+use crate::health;
+use super::MaaManager;
+use actix_web::{http::StatusCode, HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Query {
+    /// 是否返回每个实例的连接状态
+    #[serde(default)]
+    instances: bool,
+}
+
+/// 只要能处理请求就是存活的，MaaManager的锁被污染时不能。
+/// 锁被占用(如正在连接)时不等待，仍然是存活的
+pub async fn healthz(maa_manager:web::Data<Mutex<MaaManager>>) -> impl Responder {
+    if maa_manager.is_poisoned() {
+        HttpResponse::ServiceUnavailable().json(json!({ "status": "error" }))
+    } else {
+        HttpResponse::Ok().json(json!({ "status": "ok" }))
+    }
+}
+
+pub async fn readyz(query: web::Query<Query>, maa_manager:web::Data<Mutex<MaaManager>>) -> impl Responder {
+    let manager = if query.instances { maa_manager.lock().ok() } else { None };
+    let readiness = health::readiness(manager.as_deref());
+    let status = if readiness.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    HttpResponse::build(status).json(readiness)
+}
+
+/// 供探针使用GET请求
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/healthz", web::get().to(healthz));
+    cfg.route("/readyz", web::get().to(readyz));
+}
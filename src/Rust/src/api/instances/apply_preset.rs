@@ -0,0 +1,52 @@
+use std::{collections::HashMap, sync::Mutex};
+use crate::{database, metrics};
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+    name: String,
+    /// 键为任务在预设中的序号或任务类型，值中的字段会覆盖预设中的同名参数
+    #[serde(default)]
+    overrides: HashMap<String, Value>,
+}
+
+pub async fn apply_preset(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let preset = database::preset::get(&req.name)
+        .map_err(|_| Error::Internal)?
+        .ok_or(Error::PresetNotFound)?;
+    if req.overrides.values().any(|v| !v.is_object()) {
+        return Err(Error::InvalidRequest);
+    }
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    manager.get(req.id).ok_or(Error::InstanceNotFound)?;
+    let mut task_ids = Vec::with_capacity(preset.tasks.len());
+    let mut pending_ids = Vec::new();
+    for (index, task) in preset.tasks.iter().enumerate() {
+        let mut params = match &task.params {
+            Value::Object(params) => params.clone(),
+            _ => serde_json::Map::new(),
+        };
+        for key in [index.to_string(), task.type_.clone()] {
+            if let Some(Value::Object(fields)) = req.overrides.get(&key) {
+                params.extend(fields.clone());
+            }
+        }
+        // 开启了任务队列时放入队列，排在已有任务之后
+        if let Some(pending_id) = manager.enqueue(req.id, &task.type_, Value::Object(params.clone())) {
+            pending_ids.push(pending_id);
+            continue;
+        }
+        let maa = manager.get_mut(req.id).ok_or(Error::InstanceNotFound)?;
+        task_ids.push(maa.create_task(&task.type_, &Value::Object(params).to_string())?);
+        metrics::task_appended(&task.type_);
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+        "task_ids": task_ids,
+        "pending_ids": pending_ids
+    })))
+}
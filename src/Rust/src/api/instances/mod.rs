@@ -2,8 +2,10 @@ use actix_web::web;
 mod create;
 mod delete;
 mod all;
+mod apply_preset;
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.route("/instance/create", web::post().to(create::create));
     cfg.route("/instance/delete", web::post().to(delete::delete));
     cfg.route("/instance/all", web::post().to(all::all));
+    cfg.route("/instance/apply_preset", web::post().to(apply_preset::apply_preset));
 }
\ No newline at end of file
@@ -16,11 +16,13 @@ pub async fn get(req: web::Json<Req>) -> Result<impl Responder, Error> {
     let nums = req.nums.unwrap_or(i64::MAX);
     let msgs = database::msg::get_last_msg(&req.uuid, nums as usize).map_err(|_| Error::Internal)?;
     let mut ret: Vec<Value> = Vec::new();
-    for x in msgs {
+    for (id, x) in msgs {
         ret.push(json!({
+            "id":id,
             "time":x.time,
             "body":serde_json::from_str::<Value>(&x.body).map_err(|_|Error::Internal)?,
             "type":x.type_,
+            "screenshot":database::screenshot::contains(&req.uuid, id).map_err(|_|Error::Internal)?,
         }))
     }
     Ok(HttpResponse::Ok().json(json!({
@@ -1,7 +1,11 @@
 use actix_web::web;
 mod get;
 mod drop;
+mod screenshot;
+mod stats;
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.route("/message/get", web::post().to(get::get));
     cfg.route("/message/drop", web::post().to(drop::drop));
+    cfg.route("/message/screenshot", web::post().to(screenshot::screenshot));
+    cfg.route("/message/stats", web::post().to(stats::stats));
 }
\ No newline at end of file
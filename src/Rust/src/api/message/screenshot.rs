@@ -0,0 +1,18 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Req {
+    uuid: String,
+    id: u64,
+}
+
+pub async fn screenshot(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let image = database::screenshot::get(&req.uuid, req.id)
+        .map_err(|_| Error::Internal)?
+        .ok_or(Error::ScreenshotNotFound)?;
+    Ok(HttpResponse::Ok().content_type("image/png").body(image))
+}
@@ -0,0 +1,12 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{HttpResponse, Responder};
+use serde_json::json;
+
+pub async fn stats() -> Result<impl Responder, Error> {
+    Ok(HttpResponse::Ok().json(json!({
+        "stats": database::writer::stats(),
+        "result":  0,
+    })))
+}
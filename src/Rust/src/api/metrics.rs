@@ -0,0 +1,16 @@
+use std::sync::Mutex;
+use crate::metrics;
+use super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+
+pub async fn metrics(maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render(&manager)))
+}
+
+/// Prometheus使用GET抓取
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/metrics", web::get().to(metrics));
+}
@@ -1,7 +1,9 @@
-use std::{collections::HashMap, ffi::c_void};
+use std::{collections::{BTreeSet, HashMap}, ffi::c_void, sync::Mutex};
 use actix_web::{web, HttpResponse, http::{StatusCode, header::ContentType}};
-use serde_json::json;
-use crate::{maa_sys::{Maa, self}, database};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::{json, Value};
+use crate::{config::TaskSpec, maa_sys::{Maa, self}, database::{self, run::Status}};
 mod instances;
 mod connect;
 mod message;
@@ -10,6 +12,25 @@ mod device;
 mod task;
 mod uuid;
 mod run;
+mod recruit;
+mod depot;
+mod operbox;
+mod runs;
+mod schedule;
+mod preset;
+mod group;
+mod supervisor;
+mod watchdog;
+mod flow;
+mod queue;
+mod server_config;
+mod auth;
+mod metrics;
+mod health;
+lazy_static! {
+    /// 回调线程等非http请求的场合也需要访问实例
+    pub static ref MAA_MANAGER: web::Data<Mutex<MaaManager>> = web::Data::new(Mutex::new(MaaManager::new()));
+}
 pub fn config(cfg: &mut web::ServiceConfig) {
     instances::config(cfg);
     connect::config(cfg);
@@ -19,12 +40,40 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     task::config(cfg);
     uuid::config(cfg);
     run::config(cfg);
+    recruit::config(cfg);
+    depot::config(cfg);
+    operbox::config(cfg);
+    runs::config(cfg);
+    schedule::config(cfg);
+    preset::config(cfg);
+    group::config(cfg);
+    supervisor::config(cfg);
+    watchdog::config(cfg);
+    flow::config(cfg);
+    queue::config(cfg);
+    server_config::config(cfg);
+    auth::config(cfg);
+    metrics::config(cfg);
+    health::config(cfg);
 }
 #[derive(Debug)]
 pub enum Error {
     Internal,
     InstanceNotFound,
     InvalidRequest,
+    SnapshotNotFound,
+    RunNotFound,
+    ScreenshotNotFound,
+    ScheduleNotFound,
+    PresetNotFound,
+    GroupNotFound,
+    FlowNotFound,
+    InstanceBusy,
+    TaskNotFound,
+    InvalidConfig,
+    Unauthorized,
+    Forbidden,
+    KeyNotFound,
 }
 
 impl From<maa_sys::Error> for Error{
@@ -38,32 +87,79 @@ impl std::fmt::Display for Error {
     }
 }
 
+impl Error {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Error::Internal => "内部错误",
+            Error::InstanceNotFound => "实例不存在",
+            Error::InvalidRequest => "无效的请求",
+            Error::SnapshotNotFound => "快照不存在",
+            Error::RunNotFound => "运行记录不存在",
+            Error::ScreenshotNotFound => "截图不存在",
+            Error::ScheduleNotFound => "计划不存在",
+            Error::PresetNotFound => "预设不存在",
+            Error::GroupNotFound => "分组不存在",
+            Error::FlowNotFound => "流程不存在",
+            Error::InstanceBusy => "实例正在运行",
+            Error::TaskNotFound => "任务不存在",
+            Error::InvalidConfig => "配置不合法",
+            Error::Unauthorized => "未授权",
+            Error::Forbidden => "权限不足",
+            Error::KeyNotFound => "密钥不存在",
+        }
+    }
+}
+
 impl actix_web::error::ResponseError for Error {
     fn error_response(&self) -> HttpResponse {
-        let body =match self {
-            Error::Internal => json!({
-                "error":"内部错误"
-            }),
-            Error::InstanceNotFound => json!({
-                "error":"实例不存在"
-            }),
-            Error::InvalidRequest => json!({
-                "error":"无效的请求"
-            }),
-        };
+        let body = json!({
+            "error": self.message()
+        });
         HttpResponse::build(self.status_code())
             .insert_header(ContentType::json())
             .json(body)
     }
     fn status_code(&self) -> StatusCode {
         match self {
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::Forbidden => StatusCode::FORBIDDEN,
             _ => StatusCode::OK,
         }
     }
 }
  
+/// 任务参数的一次修改
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamsChange {
+    pub time:i64,
+    pub before:Value,
+    pub after:Value,
+    /// 请求中的params
+    pub patch:Value,
+}
+
+/// 每个任务最多保留的参数修改记录数
+const PARAMS_HISTORY_SIZE: usize = 50;
+
+/// 尚未添加到maa中的任务
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingTask {
+    pub pending_id:i32,
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub params:Value,
+    pub enabled:bool,
+}
+
 pub struct MaaManager{
     pub instances:HashMap<i64, Maa>,
+    /// 开启了任务队列的实例，任务在开始运行时才按顺序添加到maa中
+    queues:HashMap<i64, Vec<PendingTask>>,
+    pending_id:i32,
+    /// 每个任务的参数修改记录，键为(实例id, 任务id)
+    params_history:HashMap<(i64, i32), Vec<ParamsChange>>,
+    /// 分组名到组内实例的id
+    groups:HashMap<String, BTreeSet<i64>>,
     id:i64
 }
 
@@ -71,6 +167,10 @@ impl MaaManager {
     pub fn new()->Self{
         MaaManager { 
             instances: HashMap::new(), 
+            queues: HashMap::new(),
+            pending_id: 0,
+            params_history: HashMap::new(),
+            groups: HashMap::new(),
             id:0 
         }
     }
@@ -92,8 +192,143 @@ impl MaaManager {
     }
     pub fn delete(&mut self, id:i64)->Option<Maa>{
         let maa = self.instances.remove(&id);
+        if maa.is_some() {
+            for members in self.groups.values_mut() {
+                members.remove(&id);
+            }
+            let _ = database::run::close(id, Status::Stopped, Some("instance deleted".to_string()));
+            crate::watchdog::set(id, None);
+            self.queues.remove(&id);
+            self.params_history.retain(|(instance, _), _| *instance != id);
+        }
         maa
     }
+    pub fn push_params_change(&mut self, id:i64, task_id:i32, change:ParamsChange){
+        let history = self.params_history.entry((id, task_id)).or_default();
+        if history.len() >= PARAMS_HISTORY_SIZE {
+            history.remove(0);
+        }
+        history.push(change);
+    }
+    pub fn get_params_history(&self, id:i64, task_id:i32)->&[ParamsChange]{
+        self.params_history.get(&(id, task_id)).map_or(&[], |x| x.as_slice())
+    }
+    /// 开启或关闭实例的任务队列，关闭时队列必须为空
+    pub fn set_queue_enabled(&mut self, id:i64, enabled:bool)->Result<(), Error>{
+        self.get(id).ok_or(Error::InstanceNotFound)?;
+        if enabled {
+            self.queues.entry(id).or_default();
+        } else if self.queues.get(&id).is_some_and(|queue| !queue.is_empty()) {
+            return Err(Error::InvalidRequest);
+        } else {
+            self.queues.remove(&id);
+        }
+        Ok(())
+    }
+    /// 实例没有开启任务队列时返回None
+    pub fn get_queue(&self, id:i64)->Option<&Vec<PendingTask>>{
+        self.queues.get(&id)
+    }
+    pub fn get_queue_mut(&mut self, id:i64)->Option<&mut Vec<PendingTask>>{
+        self.queues.get_mut(&id)
+    }
+    /// 放入任务队列，实例没有开启任务队列时返回None
+    pub fn enqueue(&mut self, id:i64, type_:&str, params:Value)->Option<i32>{
+        let queue = self.queues.get_mut(&id)?;
+        self.pending_id += 1;
+        queue.push(PendingTask {
+            pending_id: self.pending_id,
+            type_: type_.to_string(),
+            params,
+            enabled: true,
+        });
+        Some(self.pending_id)
+    }
+    /// 按顺序把队列中启用的任务添加到maa中，禁用的任务留在队列中
+    fn flush_queue(&mut self, id:i64)->Result<(), Error>{
+        let queue = match self.queues.get_mut(&id) {
+            Some(queue) => queue,
+            None => return Ok(()),
+        };
+        let maa = self.instances.get_mut(&id).ok_or(Error::InstanceNotFound)?;
+        while let Some(i) = queue.iter().position(|t| t.enabled) {
+            let task = &queue[i];
+            maa.create_task(&task.type_, &task.params.to_string())?;
+            crate::metrics::task_appended(&task.type_);
+            queue.remove(i);
+        }
+        Ok(())
+    }
+    /// 开始运行并创建运行记录，返回运行记录的id
+    pub fn start(&mut self, id:i64)->Result<u64, Error>{
+        self.flush_queue(id)?;
+        self.start_run(id)
+    }
+    /// 不取出任务队列，只运行实例中已添加的任务，返回运行记录的id
+    pub fn start_run(&mut self, id:i64)->Result<u64, Error>{
+        let maa = self.get_mut(id).ok_or(Error::InstanceNotFound)?;
+        let mut tasks: Vec<(i32, String, Value)> = maa
+            .get_tasks()?
+            .values()
+            .map(|t| {
+                let params = serde_json::from_str(&t.params).unwrap_or_else(|_| Value::String(t.params.clone()));
+                (t.id, t.type_.clone(), params)
+            })
+            .collect();
+        tasks.sort_by_key(|t| t.0);
+        let uuid = maa.get_uuid().ok();
+        // 先创建记录，避免漏掉开始运行后立即到达的回调
+        let run_id = database::run::open(id, uuid, tasks).map_err(|_| Error::Internal)?;
+        if let Err(e) = maa.start() {
+            let _ = database::run::close(id, Status::Errored, Some("failed to start".to_string()));
+            return Err(e.into());
+        }
+        Ok(run_id)
+    }
+    /// 按顺序添加任务并开始运行，开启了任务队列时排在队列中已有的任务之后
+    pub fn start_tasks(&mut self, id:i64, tasks:&[TaskSpec])->Result<u64, Error>{
+        self.get(id).ok_or(Error::InstanceNotFound)?;
+        for task in tasks {
+            let params = match task.params {
+                Value::Null => json!({}),
+                ref params => params.clone(),
+            };
+            if self.enqueue(id, &task.type_, params.clone()).is_some() {
+                continue;
+            }
+            let maa = self.get_mut(id).ok_or(Error::InstanceNotFound)?;
+            maa.create_task(&task.type_, &params.to_string())?;
+            crate::metrics::task_appended(&task.type_);
+        }
+        self.start(id)
+    }
+    pub fn stop(&self, id:i64, reason:&str)->Result<(), Error>{
+        let maa = self.get(id).ok_or(Error::InstanceNotFound)?;
+        maa.stop()?;
+        database::run::close(id, Status::Stopped, Some(reason.to_string())).map_err(|_| Error::Internal)?;
+        Ok(())
+    }
+    /// 设置分组的成员，分组不存在时创建
+    pub fn set_group(&mut self, name:&str, ids:&[i64])->Result<(), Error>{
+        if ids.iter().any(|id| !self.instances.contains_key(id)) {
+            return Err(Error::InstanceNotFound);
+        }
+        self.groups.insert(name.to_string(), ids.iter().copied().collect());
+        Ok(())
+    }
+    pub fn delete_group(&mut self, name:&str)->Option<Vec<i64>>{
+        self.groups.remove(name).map(|x| x.into_iter().collect())
+    }
+    pub fn get_group(&self, name:&str)->Option<Vec<i64>>{
+        self.groups.get(name).map(|x| x.iter().copied().collect())
+    }
+    pub fn get_all_groups(&self)->HashMap<String, Vec<i64>>{
+        self.groups.iter().map(|(k, v)| (k.clone(), v.iter().copied().collect())).collect()
+    }
+    /// 销毁所有实例的句柄，只在关闭服务时调用
+    pub fn destroy_all(&mut self){
+        self.instances.clear();
+    }
     pub fn get_all_id(&self)->Vec<i64>{
         self.instances.keys().map(|x|*x).collect()
     }
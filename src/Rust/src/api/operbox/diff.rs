@@ -0,0 +1,44 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct Req {
+    uuid: String,
+    from: u64,
+    to: Option<u64>,
+}
+
+pub async fn diff(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let from = database::operbox::get(&req.uuid, req.from)
+        .map_err(|_| Error::Internal)?
+        .ok_or(Error::SnapshotNotFound)?;
+    let to = match req.to {
+        Some(to) => database::operbox::get(&req.uuid, to),
+        None => database::operbox::latest(&req.uuid),
+    }
+    .map_err(|_| Error::Internal)?
+    .ok_or(Error::SnapshotNotFound)?;
+    let diff = database::operbox::diff(&from.data, &to.data);
+    let changed: Vec<Value> = diff
+        .changed
+        .iter()
+        .map(|x| {
+            json!({
+                "before": super::oper(&x.before),
+                "after": super::oper(&x.after),
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({
+        "from": {"id": from.id, "time": from.time},
+        "to": {"id": to.id, "time": to.time},
+        "obtained": diff.obtained.iter().map(super::oper).collect::<Vec<Value>>(),
+        "lost": diff.lost.iter().map(super::oper).collect::<Vec<Value>>(),
+        "changed": changed,
+        "result":  0,
+    })))
+}
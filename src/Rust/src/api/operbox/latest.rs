@@ -0,0 +1,33 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct Req {
+    uuid: String,
+    unowned: Option<bool>,
+}
+
+pub async fn latest(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let snapshot = database::operbox::latest(&req.uuid)
+        .map_err(|_| Error::Internal)?
+        .ok_or(Error::SnapshotNotFound)?;
+    let owned: Vec<Value> = snapshot.data.owned.values().map(super::oper).collect();
+    let unowned: Option<Vec<Value>> = req
+        .unowned
+        .unwrap_or(false)
+        .then(|| snapshot.data.unowned.iter().map(super::oper).collect());
+    Ok(HttpResponse::Ok().json(json!({
+        "snapshot": {
+            "id": snapshot.id,
+            "time": snapshot.time,
+            "instance": snapshot.instance,
+            "owned": owned,
+            "unowned": unowned,
+        },
+        "result":  0,
+    })))
+}
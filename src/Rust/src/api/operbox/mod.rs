@@ -0,0 +1,27 @@
+use actix_web::web;
+use serde_json::{json, Value};
+use crate::{database::operbox::Oper, resource};
+mod latest;
+mod list;
+mod diff;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/operbox/latest", web::post().to(latest::latest));
+    cfg.route("/operbox/list", web::post().to(list::list));
+    cfg.route("/operbox/diff", web::post().to(diff::diff));
+}
+
+/// 补充battle_data.json中的干员信息
+fn oper(oper: &Oper) -> Value {
+    let info = resource::operator(&oper.id);
+    json!({
+        "id": oper.id,
+        "name": oper.name,
+        "name_en": info.as_ref().and_then(|x| x.name_en.as_ref()),
+        "profession": info.as_ref().and_then(|x| x.profession.as_ref()),
+        "position": info.as_ref().and_then(|x| x.position.as_ref()),
+        "rarity": oper.rarity.or(info.as_ref().and_then(|x| x.rarity)),
+        "elite": oper.elite,
+        "level": oper.level,
+        "potential": oper.potential,
+    })
+}
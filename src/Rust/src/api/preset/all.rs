@@ -0,0 +1,13 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{HttpResponse, Responder};
+use serde_json::json;
+
+pub async fn all() -> Result<impl Responder, Error> {
+    let presets = database::preset::all().map_err(|_| Error::Internal)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "presets": presets,
+        "result":  0,
+    })))
+}
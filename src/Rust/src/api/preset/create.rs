@@ -0,0 +1,20 @@
+use crate::database::{self, preset::Preset};
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde_json::{json, Value};
+
+/// 名称相同时覆盖原有的预设
+pub async fn create(req: web::Json<Preset>) -> Result<impl Responder, Error> {
+    let preset = req.into_inner();
+    if preset.name.is_empty()
+        || preset.tasks.is_empty()
+        || preset.tasks.iter().any(|t| !matches!(t.params, Value::Null | Value::Object(_)))
+    {
+        return Err(Error::InvalidRequest);
+    }
+    database::preset::save(&preset).map_err(|_| Error::Internal)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
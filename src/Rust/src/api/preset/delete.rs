@@ -0,0 +1,20 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+}
+
+pub async fn delete(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    if !database::preset::remove(&req.name).map_err(|_| Error::Internal)? {
+        return Err(Error::PresetNotFound);
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
@@ -0,0 +1,21 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+}
+
+pub async fn get(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let preset = database::preset::get(&req.name)
+        .map_err(|_| Error::Internal)?
+        .ok_or(Error::PresetNotFound)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "preset": preset,
+        "result":  0,
+    })))
+}
@@ -0,0 +1,11 @@
+use actix_web::web;
+mod create;
+mod get;
+mod all;
+mod delete;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/preset/create", web::post().to(create::create));
+    cfg.route("/preset/get", web::post().to(get::get));
+    cfg.route("/preset/all", web::post().to(all::all));
+    cfg.route("/preset/delete", web::post().to(delete::delete));
+}
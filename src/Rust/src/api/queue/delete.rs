@@ -0,0 +1,20 @@
+use std::sync::Mutex;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+    pending_id: i32,
+}
+
+pub async fn delete(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    let (queue, position) = super::find(&mut manager, req.id, req.pending_id)?;
+    queue.remove(position);
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
@@ -0,0 +1,19 @@
+use std::sync::Mutex;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+    enabled: bool,
+}
+
+pub async fn enable(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    manager.set_queue_enabled(req.id, req.enabled)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
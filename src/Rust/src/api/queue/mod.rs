@@ -0,0 +1,23 @@
+use actix_web::web;
+use super::{Error, MaaManager, PendingTask};
+mod enable;
+mod delete;
+mod reorder;
+mod set;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/queue/enable", web::post().to(enable::enable));
+    cfg.route("/queue/delete", web::post().to(delete::delete));
+    cfg.route("/queue/move", web::post().to(reorder::reorder));
+    cfg.route("/queue/set", web::post().to(set::set));
+}
+
+/// 返回任务队列和任务在队列中的位置
+fn find(manager: &mut MaaManager, id: i64, pending_id: i32) -> Result<(&mut Vec<PendingTask>, usize), Error> {
+    manager.get(id).ok_or(Error::InstanceNotFound)?;
+    let queue = manager.get_queue_mut(id).ok_or(Error::InvalidRequest)?;
+    let position = queue
+        .iter()
+        .position(|t| t.pending_id == pending_id)
+        .ok_or(Error::TaskNotFound)?;
+    Ok((queue, position))
+}
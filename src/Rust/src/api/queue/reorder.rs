@@ -0,0 +1,24 @@
+use std::sync::Mutex;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+    pending_id: i32,
+    /// 在队列中的新位置，从0开始，超出时移到最后
+    position: usize,
+}
+
+pub async fn reorder(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    let (queue, position) = super::find(&mut manager, req.id, req.pending_id)?;
+    let task = queue.remove(position);
+    let position = req.position.min(queue.len());
+    queue.insert(position, task);
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
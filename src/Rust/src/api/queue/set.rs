@@ -0,0 +1,32 @@
+use std::sync::Mutex;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+    pending_id: i32,
+    /// 替换原有的参数
+    params: Option<Value>,
+    enabled: Option<bool>,
+}
+
+pub async fn set(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    if req.params.as_ref().is_some_and(|params| !params.is_object()) {
+        return Err(Error::InvalidRequest);
+    }
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    let (queue, position) = super::find(&mut manager, req.id, req.pending_id)?;
+    let task = &mut queue[position];
+    if let Some(params) = &req.params {
+        task.params = params.clone();
+    }
+    if let Some(enabled) = req.enabled {
+        task.enabled = enabled;
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
@@ -0,0 +1,31 @@
+use crate::database::{self, recruit::Filter};
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    uuid: Option<String>,
+    id: Option<i64>,
+    begin: Option<i64>,
+    end: Option<i64>,
+    nums: Option<i64>,
+}
+
+pub async fn history(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let req = req.into_inner();
+    let nums = req.nums.unwrap_or(i64::MAX);
+    let filter = Filter {
+        uuid: req.uuid,
+        instance: req.id,
+        begin: req.begin,
+        end: req.end,
+    };
+    let records = database::recruit::get_records(&filter, nums as usize).map_err(|_| Error::Internal)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "records": records,
+        "result":  0,
+    })))
+}
@@ -0,0 +1,7 @@
+use actix_web::web;
+mod history;
+mod summary;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/recruit/history", web::post().to(history::history));
+    cfg.route("/recruit/summary", web::post().to(summary::summary));
+}
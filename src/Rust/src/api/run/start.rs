@@ -11,10 +11,10 @@ pub struct Req {
 }
 
 pub async fn start(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>)-> Result<impl Responder, Error>{
-    let manager = maa_manager.lock().map_err(|_|Error::Internal)?;
-    let maa = manager.get(req.id).ok_or(Error::InstanceNotFound)?;
-    maa.start()?;
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    let run_id = manager.start(req.id)?;
     Ok(HttpResponse::Ok().json(json!({
 		"result":  0,
+        "run_id": run_id,
     })))
 }
@@ -12,8 +12,7 @@ pub struct Req {
 
 pub async fn stop(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>)-> Result<impl Responder, Error>{
     let manager = maa_manager.lock().map_err(|_|Error::Internal)?;
-    let maa = manager.get(req.id).ok_or(Error::InstanceNotFound)?;
-    maa.stop()?;
+    manager.stop(req.id, "stopped by request")?;
     Ok(HttpResponse::Ok().json(json!({
 		"result":  0,
     })))
@@ -0,0 +1,27 @@
+use crate::database::{self, run::Filter};
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: Option<i64>,
+    uuid: Option<String>,
+    nums: Option<i64>,
+}
+
+pub async fn all(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let req = req.into_inner();
+    let nums = req.nums.unwrap_or(i64::MAX);
+    let filter = Filter {
+        instance: req.id,
+        uuid: req.uuid,
+    };
+    let runs = database::run::list(&filter, nums as usize).map_err(|_| Error::Internal)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "runs": runs,
+        "result":  0,
+    })))
+}
@@ -0,0 +1,21 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    run_id: u64,
+}
+
+pub async fn get(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let run = database::run::get(req.run_id)
+        .map_err(|_| Error::Internal)?
+        .ok_or(Error::RunNotFound)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "run": run,
+        "result":  0,
+    })))
+}
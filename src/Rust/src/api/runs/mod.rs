@@ -0,0 +1,7 @@
+use actix_web::web;
+mod all;
+mod get;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/runs/all", web::post().to(all::all));
+    cfg.route("/runs/get", web::post().to(get::get));
+}
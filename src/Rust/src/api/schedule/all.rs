@@ -0,0 +1,27 @@
+use crate::{config, scheduler};
+
+use super::super::Error;
+use actix_web::{HttpResponse, Responder};
+use serde_json::json;
+
+pub async fn all() -> Result<impl Responder, Error> {
+    let schedules: Vec<_> = scheduler::schedules()
+        .into_iter()
+        .map(|schedule| {
+            let source = if config::current().schedules.iter().any(|x| x.name == schedule.name) {
+                "config"
+            } else {
+                "api"
+            };
+            json!({
+                "next": scheduler::upcoming(&schedule, 1).first(),
+                "source": source,
+                "schedule": schedule,
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({
+        "schedules": schedules,
+        "result":  0,
+    })))
+}
@@ -0,0 +1,22 @@
+use crate::{config::{self, Schedule}, database, scheduler};
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde_json::json;
+
+/// 名称相同时覆盖之前通过接口添加的计划
+pub async fn create(req: web::Json<Schedule>) -> Result<impl Responder, Error> {
+    let schedule = req.into_inner();
+    if let Err(e) = scheduler::validate(&schedule) {
+        tracing::warn!("invalid schedule {}: {}", schedule.name, e);
+        return Err(Error::InvalidRequest);
+    }
+    if config::current().schedules.iter().any(|x| x.name == schedule.name) {
+        return Err(Error::InvalidRequest);
+    }
+    database::schedule::save(&schedule).map_err(|_| Error::Internal)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "next": scheduler::upcoming(&schedule, 1).first(),
+        "result":  0,
+    })))
+}
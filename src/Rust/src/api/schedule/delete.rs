@@ -0,0 +1,21 @@
+use crate::database;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+}
+
+/// 只能删除通过接口添加的计划
+pub async fn delete(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    if !database::schedule::remove(&req.name).map_err(|_| Error::Internal)? {
+        return Err(Error::ScheduleNotFound);
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "result":  0,
+    })))
+}
@@ -0,0 +1,28 @@
+use crate::{database, scheduler};
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    name: String,
+    nums: Option<i64>,
+    upcoming: Option<i64>,
+}
+
+pub async fn history(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    let schedule = scheduler::schedules()
+        .into_iter()
+        .find(|x| x.name == req.name)
+        .ok_or(Error::ScheduleNotFound)?;
+    let nums = req.nums.unwrap_or(i64::MAX);
+    let history = database::schedule::history(&schedule.name, nums as usize).map_err(|_| Error::Internal)?;
+    let upcoming = scheduler::upcoming(&schedule, req.upcoming.unwrap_or(5) as usize);
+    Ok(HttpResponse::Ok().json(json!({
+        "history": history,
+        "upcoming": upcoming,
+        "result":  0,
+    })))
+}
@@ -0,0 +1,11 @@
+use actix_web::web;
+mod all;
+mod create;
+mod delete;
+mod history;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/schedule/all", web::post().to(all::all));
+    cfg.route("/schedule/create", web::post().to(create::create));
+    cfg.route("/schedule/delete", web::post().to(delete::delete));
+    cfg.route("/schedule/history", web::post().to(history::history));
+}
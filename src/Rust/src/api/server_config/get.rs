@@ -0,0 +1,14 @@
+use crate::config;
+
+use super::super::Error;
+use actix_web::{HttpResponse, Responder};
+use serde_json::json;
+
+pub async fn get() -> Result<impl Responder, Error> {
+    // 不返回密钥本身
+    let config = config::current().redacted();
+    Ok(HttpResponse::Ok().json(json!({
+        "config": config,
+        "result":  0,
+    })))
+}
@@ -0,0 +1,7 @@
+use actix_web::web;
+mod get;
+mod reload;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/config/get", web::post().to(get::get));
+    cfg.route("/config/reload", web::post().to(reload::reload));
+}
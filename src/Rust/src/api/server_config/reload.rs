@@ -0,0 +1,17 @@
+use crate::reload;
+
+use super::super::Error;
+use actix_web::{HttpResponse, Responder};
+use serde_json::json;
+
+pub async fn reload() -> Result<impl Responder, Error> {
+    let result = reload::reload().map_err(|e| {
+        tracing::warn!("failed to reload config: {}", e);
+        Error::InvalidConfig
+    })?;
+    Ok(HttpResponse::Ok().json(json!({
+        "applied": result.applied,
+        "restart_required": result.restart_required,
+        "result":  0,
+    })))
+}
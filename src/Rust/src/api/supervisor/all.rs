@@ -0,0 +1,12 @@
+use crate::supervisor;
+
+use super::super::Error;
+use actix_web::{HttpResponse, Responder};
+use serde_json::json;
+
+pub async fn all() -> Result<impl Responder, Error> {
+    Ok(HttpResponse::Ok().json(json!({
+        "supervisors": supervisor::all(),
+        "result":  0,
+    })))
+}
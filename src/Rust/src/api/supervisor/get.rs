@@ -0,0 +1,19 @@
+use crate::supervisor;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+}
+
+/// 实例从未断开过时supervisor为null
+pub async fn get(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    Ok(HttpResponse::Ok().json(json!({
+        "supervisor": supervisor::get(req.id),
+        "result":  0,
+    })))
+}
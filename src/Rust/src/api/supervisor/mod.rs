@@ -0,0 +1,7 @@
+use actix_web::web;
+mod get;
+mod all;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/supervisor/get", web::post().to(get::get));
+    cfg.route("/supervisor/all", web::post().to(all::all));
+}
@@ -2,7 +2,8 @@ use std::{sync::Mutex, collections::HashMap};
 use super::super::{Error, MaaManager};
 use actix_web::{HttpResponse, Responder, web};
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{json, Value};
+use crate::database::{self, run::Status};
 
 #[allow(dead_code)]
 #[derive(Deserialize)]
@@ -13,18 +14,53 @@ pub struct Req {
 pub async fn all(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
     let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
     let maa = manager.get_mut(req.id).ok_or(Error::InstanceNotFound)?;
-    let tasks = {
-        let mut tmp = HashMap::new();
-        for (k, v) in maa.get_tasks()?{
-            tmp.insert(k.to_string(), json!({
-                "type": v.type_,
-                "params": v.params
-            }));
-        };
-        tmp
-    };
+    // maa按task_id的顺序运行任务，队列中的任务排在之后
+    let mut ids: Vec<i32> = maa.get_tasks()?.keys().copied().collect();
+    ids.sort_unstable();
+    // 任务的状态来自最近的运行记录，已结束的任务保留到下次开始运行
+    let run_tasks = database::run::latest(req.id).map(|run| run.tasks).unwrap_or_default();
+    let state = |task_id: i32| run_tasks.iter().find(|t| t.task_id == task_id);
+    let mut tasks = HashMap::new();
+    for (k, v) in maa.get_tasks()?{
+        let params = serde_json::from_str(&v.params).unwrap_or_else(|_| Value::String(v.params.clone()));
+        let task = state(*k);
+        tasks.insert(k.to_string(), json!({
+            "type": v.type_,
+            "params": params,
+            "position": ids.iter().position(|x| x == k),
+            "status": task.map_or(Status::Pending, |t| t.status),
+            "started": task.and_then(|t| t.started),
+            "finished": task.and_then(|t| t.finished),
+            "error": task.and_then(|t| t.error.clone()),
+        }));
+    }
+    for task in run_tasks.iter().filter(|t| !ids.contains(&t.task_id)) {
+        tasks.insert(task.task_id.to_string(), json!({
+            "type": task.type_,
+            "params": task.params,
+            "position": null,
+            "status": task.status,
+            "started": task.started,
+            "finished": task.finished,
+            "error": task.error,
+        }));
+    }
+    let pending: Option<Vec<_>> = manager.get_queue(req.id).map(|queue| {
+        queue
+            .iter()
+            .enumerate()
+            .map(|(i, task)| json!({
+                "pending_id": task.pending_id,
+                "type": task.type_,
+                "params": task.params,
+                "enabled": task.enabled,
+                "position": ids.len() + i
+            }))
+            .collect()
+    });
     Ok(HttpResponse::Ok().json(json!({
         "tasks":tasks,
+        "pending":pending,
         "result":  0,
     })))
 }
\ No newline at end of file
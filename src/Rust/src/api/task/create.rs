@@ -1,4 +1,5 @@
 use std::sync::Mutex;
+use crate::metrics;
 use super::super::{Error, MaaManager};
 use actix_web::{HttpResponse, Responder, web};
 use serde::Deserialize;
@@ -14,13 +15,23 @@ pub struct Req {
 }
 pub async fn create(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
     let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
-    let maa = manager.get_mut(req.id).ok_or(Error::InstanceNotFound)?;
+    manager.get(req.id).ok_or(Error::InstanceNotFound)?;
     let params = match req.params {
-        Value::Null => "{}".to_string(),
-        Value::Object(_) => req.params.to_string(),
+        Value::Null => json!({}),
+        Value::Object(_) => req.params.clone(),
         _=>return Err(Error::InvalidRequest)
     };
-    let task_id = maa.create_task(&req.types, &params)?;
+    // 开启了任务队列时，任务在开始运行时才添加到maa中
+    if let Some(pending_id) = manager.enqueue(req.id, &req.types, params.clone()) {
+        return Ok(HttpResponse::Ok().json(json!({
+            "result":  0,
+            "task_id": null,
+            "pending_id": pending_id
+        })));
+    }
+    let maa = manager.get_mut(req.id).ok_or(Error::InstanceNotFound)?;
+    let task_id = maa.create_task(&req.types, &params.to_string())?;
+    metrics::task_appended(&req.types);
     Ok(HttpResponse::Ok().json(json!({
         "result":  0,
         "task_id": task_id
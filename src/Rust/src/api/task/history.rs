@@ -0,0 +1,20 @@
+use std::sync::Mutex;
+use super::super::{Error, MaaManager};
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+    task_id: i32,
+}
+
+pub async fn history(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    let manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    manager.get(req.id).ok_or(Error::InstanceNotFound)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "history": manager.get_params_history(req.id, req.task_id),
+        "result":  0,
+    })))
+}
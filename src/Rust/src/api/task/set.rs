@@ -1,27 +1,103 @@
 use std::sync::Mutex;
-use super::super::{Error, MaaManager};
+use super::super::{Error, MaaManager, ParamsChange};
 use actix_web::{HttpResponse, Responder, web};
 use serde::Deserialize;
 use serde_json::{json, Value};
 
-#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct Req {
     id: i64,
     task_id: i32,
     params: Value,
+    /// 为true时用params替换原有的参数，否则作为JSON Merge Patch(RFC 7396)合并
+    #[serde(default)]
+    replace: bool,
+}
 
+/// RFC 7396: patch中值为null的字段会被删除，对象递归合并，其余值直接替换
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        _ => {
+            *target = patch.clone();
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = json!({});
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
 }
+
 pub async fn set(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
-    let manager = maa_manager.lock().map_err(|_|Error::Internal)?;
-    let maa = manager.get(req.id).ok_or(Error::InstanceNotFound)?;
-    let params = match req.params {
-        Value::Null => "{}".to_string(),
-        Value::Object(_) => req.params.to_string(),
-        _=>return Err(Error::InvalidRequest)
+    let mut manager = maa_manager.lock().map_err(|_|Error::Internal)?;
+    let maa = manager.get_mut(req.id).ok_or(Error::InstanceNotFound)?;
+    let task = maa.get_tasks()?.get(&req.task_id).ok_or(Error::TaskNotFound)?;
+    let before: Value = serde_json::from_str(&task.params).unwrap_or_else(|_| json!({}));
+    let after = if req.replace {
+        match req.params {
+            Value::Null => json!({}),
+            _ => req.params.clone(),
+        }
+    } else {
+        let mut after = before.clone();
+        merge_patch(&mut after, &req.params);
+        after
     };
-    maa.set_task(req.task_id, &params)?;
+    if !after.is_object() {
+        return Err(Error::InvalidRequest)
+    }
+    maa.set_task(req.task_id, &after.to_string())?;
+    manager.push_params_change(req.id, req.task_id, ParamsChange {
+        time: chrono::Local::now().timestamp_millis(),
+        before,
+        after: after.clone(),
+        patch: req.params.clone(),
+    });
     Ok(HttpResponse::Ok().json(json!({
+        "params": after,
         "result":  0,
     })))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patched(target: Value, patch: Value) -> Value {
+        let mut target = target;
+        merge_patch(&mut target, &patch);
+        target
+    }
+
+    #[test]
+    fn merge_patch_merges_objects_recursively() {
+        let target = json!({"stage": "1-7", "times": 3, "drops": {"30011": 10}});
+        let patch = json!({"stage": "CE-5", "drops": {"30012": 5}});
+        assert_eq!(
+            patched(target, patch),
+            json!({"stage": "CE-5", "times": 3, "drops": {"30011": 10, "30012": 5}})
+        );
+    }
+
+    #[test]
+    fn merge_patch_removes_null_fields() {
+        let target = json!({"stage": "1-7", "times": 3, "drops": {"30011": 10}});
+        let patch = json!({"times": null, "drops": {"30011": null}});
+        assert_eq!(patched(target, patch), json!({"stage": "1-7", "drops": {}}));
+    }
+
+    #[test]
+    fn merge_patch_replaces_non_object_values() {
+        assert_eq!(patched(json!({"a": [1, 2]}), json!({"a": [3]})), json!({"a": [3]}));
+        assert_eq!(patched(json!({"a": 1}), json!([1])), json!([1]));
+        assert_eq!(patched(json!("text"), json!({"a": null, "b": 1})), json!({"b": 1}));
+    }
+}
@@ -0,0 +1,19 @@
+use crate::watchdog;
+
+use super::super::Error;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+}
+
+pub async fn get(req: web::Json<Req>) -> Result<impl Responder, Error> {
+    Ok(HttpResponse::Ok().json(json!({
+        "settings": watchdog::settings(req.id),
+        "state": watchdog::get(req.id),
+        "result":  0,
+    })))
+}
@@ -0,0 +1,7 @@
+use actix_web::web;
+mod get;
+mod set;
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/watchdog/get", web::post().to(get::get));
+    cfg.route("/watchdog/set", web::post().to(set::set));
+}
@@ -0,0 +1,34 @@
+use std::sync::Mutex;
+use crate::{config, watchdog};
+
+use super::super::{Error, MaaManager};
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct Req {
+    id: i64,
+    settings: Value,
+}
+
+/// settings中省略的字段使用配置文件中的值，settings为null时恢复使用配置文件中的设置
+pub async fn set(req: web::Json<Req>, maa_manager:web::Data<Mutex<MaaManager>>) -> Result<impl Responder, Error> {
+    maa_manager.lock().map_err(|_|Error::Internal)?.get(req.id).ok_or(Error::InstanceNotFound)?;
+    let settings = match &req.settings {
+        Value::Null => None,
+        Value::Object(fields) => {
+            let mut settings = serde_json::to_value(&config::current().watchdog).map_err(|_| Error::Internal)?;
+            if let Value::Object(base) = &mut settings {
+                base.extend(fields.clone());
+            }
+            Some(serde_json::from_value(settings).map_err(|_| Error::InvalidRequest)?)
+        }
+        _ => return Err(Error::InvalidRequest),
+    };
+    watchdog::set(req.id, settings);
+    Ok(HttpResponse::Ok().json(json!({
+        "settings": watchdog::settings(req.id),
+        "result":  0,
+    })))
+}
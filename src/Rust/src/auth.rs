@@ -0,0 +1,243 @@
+use lazy_static::lazy_static;
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::AUTHORIZATION,
+    web,
+};
+use rand::RngCore;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, PoisonError, RwLock};
+use crate::{
+    api::Error,
+    config::{self, Scope},
+    database::{self, auth::StoredKey},
+};
+
+/// 每个接口需要的权限，以及请求中的id是否为实例id，未列出的接口需要admin
+const ROUTES: &[(&str, Scope, bool)] = &[
+    ("/version", Scope::Read, false),
+    ("/metrics", Scope::Read, false),
+    ("/readyz", Scope::Read, false),
+    ("/instance/all", Scope::Read, false),
+    ("/connect/target", Scope::Read, true),
+    ("/task/all", Scope::Read, true),
+    ("/task/history", Scope::Read, true),
+    ("/uuid/all", Scope::Read, false),
+    ("/uuid/get", Scope::Read, true),
+    ("/message/get", Scope::Read, false),
+    ("/message/screenshot", Scope::Read, false),
+    ("/message/stats", Scope::Read, false),
+    ("/recruit/history", Scope::Read, true),
+    ("/recruit/summary", Scope::Read, true),
+    ("/depot/latest", Scope::Read, false),
+    ("/depot/list", Scope::Read, false),
+    ("/depot/diff", Scope::Read, false),
+    ("/operbox/latest", Scope::Read, false),
+    ("/operbox/list", Scope::Read, false),
+    ("/operbox/diff", Scope::Read, false),
+    ("/runs/all", Scope::Read, true),
+    ("/runs/get", Scope::Read, false),
+    ("/schedule/all", Scope::Read, false),
+    ("/schedule/history", Scope::Read, false),
+    ("/preset/all", Scope::Read, false),
+    ("/preset/get", Scope::Read, false),
+    ("/group/all", Scope::Read, false),
+    ("/supervisor/get", Scope::Read, true),
+    ("/supervisor/all", Scope::Read, false),
+    ("/watchdog/get", Scope::Read, true),
+    ("/flow/get", Scope::Read, false),
+    ("/flow/all", Scope::Read, true),
+    ("/task/create", Scope::Control, true),
+    ("/task/set", Scope::Control, true),
+    ("/run/start", Scope::Control, true),
+    ("/run/stop", Scope::Control, true),
+    ("/device/click", Scope::Control, true),
+    ("/device/screenshot", Scope::Control, true),
+    ("/queue/enable", Scope::Control, true),
+    ("/queue/delete", Scope::Control, true),
+    ("/queue/move", Scope::Control, true),
+    ("/queue/set", Scope::Control, true),
+    ("/instance/apply_preset", Scope::Control, true),
+    ("/watchdog/set", Scope::Control, true),
+    ("/flow/start", Scope::Control, true),
+    ("/flow/stop", Scope::Control, false),
+    ("/preset/create", Scope::Control, false),
+    ("/preset/delete", Scope::Control, false),
+    ("/schedule/create", Scope::Control, false),
+    ("/schedule/delete", Scope::Control, false),
+    ("/group/task/create", Scope::Control, false),
+    ("/group/start", Scope::Control, false),
+    ("/group/stop", Scope::Control, false),
+    ("/group/back_to_home", Scope::Control, false),
+    ("/instance/delete", Scope::Admin, true),
+    ("/connect/attach", Scope::Admin, true),
+];
+
+/// 不需要认证的接口，供探针使用。/readyz返回实例详情时仍需要认证
+const PUBLIC: &[&str] = &["/healthz", "/readyz"];
+
+lazy_static! {
+    /// 数据库中的密钥，第一次认证时读取，添加或删除密钥后通过invalidate()清空
+    static ref STORED: RwLock<Option<Arc<Vec<StoredKey>>>> = RwLock::new(None);
+}
+
+/// 请求使用的密钥
+struct Identity {
+    scope: Scope,
+    instances: Option<Vec<i64>>,
+}
+
+pub fn hash(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 随机生成32字节的密钥
+pub fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 数据库中的密钥变化后调用，下一次认证时重新读取
+pub fn invalidate() {
+    *STORED.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+fn stored() -> Result<Arc<Vec<StoredKey>>, Error> {
+    if let Some(stored) = STORED.read().unwrap_or_else(PoisonError::into_inner).as_ref() {
+        return Ok(stored.clone());
+    }
+    // 持有写锁读取数据库，读取期间的invalidate()会等到读取完成后再清空
+    let mut cache = STORED.write().unwrap_or_else(PoisonError::into_inner);
+    if let Some(stored) = cache.as_ref() {
+        return Ok(stored.clone());
+    }
+    let stored = Arc::new(database::auth::all().map_err(|_| Error::Internal)?);
+    *cache = Some(stored.clone());
+    Ok(stored)
+}
+
+/// 返回None时没有任何密钥，不需要认证
+fn identify(token: Option<&str>) -> Result<Option<Identity>, Error> {
+    let config = config::current();
+    let stored = stored()?;
+    if config.auth.keys.is_empty() && stored.is_empty() {
+        return Ok(None);
+    }
+    let hash = hash(token.ok_or(Error::Unauthorized)?);
+    if let Some(key) = config.auth.keys.iter().find(|x| x.hash == hash) {
+        return Ok(Some(Identity {
+            scope: key.scope,
+            instances: key.instances.clone(),
+        }));
+    }
+    match stored.iter().find(|x| x.hash == hash) {
+        Some(key) => Ok(Some(Identity {
+            scope: key.scope,
+            instances: key.instances.clone(),
+        })),
+        None => Err(Error::Unauthorized),
+    }
+}
+
+/// 请求是否要求返回实例的连接目标等详情
+fn instance_details(req: &ServiceRequest) -> bool {
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .is_ok_and(|query| query.get("instances").is_some_and(|x| x == "true"))
+}
+
+async fn authorize(req: &mut ServiceRequest) -> Result<(), Error> {
+    if PUBLIC.contains(&req.path()) && !instance_details(req) {
+        return Ok(());
+    }
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .map(str::trim);
+    let identity = match identify(token)? {
+        Some(identity) => identity,
+        None => return Ok(()),
+    };
+    let (scope, by_instance) = ROUTES
+        .iter()
+        .find(|(path, _, _)| *path == req.path())
+        .map_or((Scope::Admin, false), |(_, scope, by_instance)| (*scope, *by_instance));
+    if identity.scope < scope {
+        return Err(Error::Forbidden);
+    }
+    let instances = match identity.instances {
+        Some(instances) if req.path() != "/version" => instances,
+        _ => return Ok(()),
+    };
+    // 限制了实例的密钥只能访问请求中带有实例id的接口
+    if !by_instance {
+        return Err(Error::Forbidden);
+    }
+    let body = req.extract::<web::Bytes>().await.map_err(|_| Error::InvalidRequest)?;
+    let id = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .and_then(|x| x.get("id").and_then(Value::as_i64));
+    req.set_payload(Payload::from(body));
+    match id {
+        Some(id) if instances.contains(&id) => Ok(()),
+        _ => Err(Error::Forbidden),
+    }
+}
+
+/// 检查Authorization头中的密钥
+pub struct Auth;
+
+impl<S, B> Transform<S, ServiceRequest> for Auth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = AuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct AuthMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(async move {
+            match authorize(&mut req).await {
+                Ok(()) => service.call(req).await.map(ServiceResponse::map_into_left_body),
+                Err(e) => Ok(req.error_response(e).map_into_right_body()),
+            }
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{mpsc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use crate::{
+    api::MAA_MANAGER,
+    config,
+    database::{self, msg::Msg},
+    flow,
+    maa_sys::{self, AsstMsg, Maa},
+    metrics,
+    supervisor,
+    watchdog,
+};
+
+/// 等待截图完成的最长时间
+const SCREENCAP_TIMEOUT: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    /// 等待结果的异步调用，键为实例id和async_call_id
+    static ref ASYNC_CALLS: Mutex<HashMap<(i64, i64), mpsc::Sender<bool>>> = Mutex::new(HashMap::new());
+}
+
+/// 将回调消息分发给各个记录模块，msg_id为None时消息没有入库
+pub fn dispatch(instance: i64, msg_id: Option<u64>, msg: &Msg, value: &Value) {
+    let type_ = match AsstMsg::from_u32(msg.type_) {
+        Some(type_) => type_,
+        None => return,
+    };
+    let task_id = value.get("taskid").and_then(Value::as_i64);
+    let _span = tracing::info_span!("callback", instance, task_id, msg = ?type_).entered();
+    // 按uuid保存的记录需要消息带有uuid
+    if !msg.uuid.is_empty() {
+        if let Err(e) = database::recruit::on_callback(instance, &msg.uuid, type_, value) {
+            tracing::warn!("failed to record recruitment: {:?}", e);
+        }
+        if let Err(e) = database::depot::on_callback(instance, &msg.uuid, type_, value) {
+            tracing::warn!("failed to record depot: {:?}", e);
+        }
+        if let Err(e) = database::operbox::on_callback(instance, &msg.uuid, type_, value) {
+            tracing::warn!("failed to record operator box: {:?}", e);
+        }
+    }
+    if let Err(e) = database::run::on_callback(instance, type_, value) {
+        tracing::warn!("failed to update run history: {:?}", e);
+    }
+    metrics::on_callback(type_, value);
+    if type_ == AsstMsg::AsyncCallInfo {
+        on_async_call(instance, value);
+    }
+    watchdog::on_callback(instance, type_, value);
+    flow::on_callback(instance, type_, value);
+    if config::current().supervisor.enabled {
+        supervisor::on_callback(instance, type_, value);
+    }
+    let screenshot = &config::current().screenshot;
+    // 截图按消息id保存，只对已入库的消息截图
+    if let Some(msg_id) = msg_id {
+        if screenshot.enabled && screenshot.msg_types.iter().any(|x| *x == format!("{:?}", type_)) {
+            capture(instance, msg_id, msg.uuid.clone());
+        }
+    }
+}
+
+/// 异步调用完成时通知call_async
+fn on_async_call(instance: i64, value: &Value) {
+    let call_id = match value.get("async_call_id").and_then(Value::as_i64) {
+        Some(call_id) => call_id,
+        None => return,
+    };
+    let ret = value
+        .get("details")
+        .and_then(|x| x.get("ret"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    if let Some(sender) = ASYNC_CALLS.lock().unwrap_or_else(PoisonError::into_inner).remove(&(instance, call_id)) {
+        let _ = sender.send(ret);
+    }
+}
+
+/// 持有MAA_MANAGER发起异步调用，释放后等待AsyncCallInfo中的结果，等待时不阻塞其他请求。
+/// 不能在写入线程中调用
+pub fn call_async<F>(instance: i64, timeout: Duration, call: F) -> Result<(), String>
+where
+    F: FnOnce(&mut Maa) -> Result<i32, maa_sys::Error>,
+{
+    let (sender, receiver) = mpsc::channel();
+    let call_id = {
+        let mut manager = MAA_MANAGER.lock().map_err(|_| "maa manager is poisoned".to_string())?;
+        let maa = manager.get_mut(instance).ok_or("instance not found")?;
+        // 持有ASYNC_CALLS直到记下call_id，避免结果先于call_id被处理
+        let mut calls = ASYNC_CALLS.lock().unwrap_or_else(PoisonError::into_inner);
+        let call_id = call(maa).map_err(|e| format!("{:?}", e))? as i64;
+        calls.insert((instance, call_id), sender);
+        call_id
+    };
+    let ret = receiver.recv_timeout(timeout);
+    ASYNC_CALLS.lock().unwrap_or_else(PoisonError::into_inner).remove(&(instance, call_id));
+    match ret {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("failed".to_string()),
+        Err(_) => Err("timed out".to_string()),
+    }
+}
+
+/// 只在发起截图和读取图片时持有MAA_MANAGER
+fn screenshot(instance: i64) -> Result<Vec<u8>, String> {
+    call_async(instance, SCREENCAP_TIMEOUT, Maa::request_screenshot).map_err(|e| format!("screencap {}", e))?;
+    let manager = MAA_MANAGER.lock().map_err(|_| "maa manager is poisoned".to_string())?;
+    let maa = manager.get(instance).ok_or("instance not found")?;
+    maa.screenshot().map_err(|e| format!("{:?}", e))
+}
+
+/// 在新线程中截图，不阻塞回调线程
+fn capture(instance: i64, msg_id: u64, uuid: String) {
+    std::thread::spawn(move || {
+        let start = Instant::now();
+        let image = screenshot(instance);
+        metrics::observe_screenshot("capture", start.elapsed());
+        match image {
+            Ok(image) => {
+                if let Err(e) = database::screenshot::insert(&uuid, msg_id, &image) {
+                    tracing::warn!("failed to save screenshot of message {}: {:?}", msg_id, e);
+                }
+            }
+            Err(e) => tracing::warn!("failed to take screenshot of instance {}: {}", instance, e),
+        }
+    });
+}
@@ -1,19 +1,182 @@
+use clap::{Parser, Subcommand};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use crate::maa_sys::AsstStaticOptionKey;
+
+const DEFAULT_CONFIG_PATH: &str = "./server_config.json";
+/// 环境变量的前缀，如MAA_SERVER_PORT对应server.port
+const ENV_PREFIX: &str = "MAA_";
 
 lazy_static! {
-    pub static ref CONFIG: Config = {
-        let config_file = Path::new("./server_config.json");
-        if !config_file.exists() {
-            let default_config = include_str!("../server_config.json");
-            fs::write(config_file,default_config).unwrap();
-        }
-        let s = fs::read(config_file).unwrap();
-        let r: Config = serde_json::from_slice(&s).unwrap();
-        r
+    pub static ref ARGS: Args = Args::parse();
+    /// 重新加载时整个替换，通过current()读取
+    static ref CURRENT: RwLock<Arc<Config>> = RwLock::new(Arc::new(match load(&ARGS) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }));
+}
+
+/// 当前生效的配置，配置不合法时退出进程
+pub fn current() -> Arc<Config> {
+    CURRENT.read().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+pub fn replace(config: Config) {
+    *CURRENT.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+}
+
+#[derive(Parser)]
+#[clap(version, about = "MAA http api server")]
+pub struct Args {
+    /// 配置文件路径，默认为./server_config.json，不存在时会写入默认配置
+    #[clap(long, short, env = "MAA_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// 覆盖server.address
+    #[clap(long)]
+    pub address: Option<String>,
+
+    /// 覆盖server.port
+    #[clap(long)]
+    pub port: Option<u16>,
+
+    /// 覆盖database.path
+    #[clap(long)]
+    pub database_path: Option<String>,
+
+    /// 覆盖resource.path
+    #[clap(long)]
+    pub resource_path: Option<String>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// 检查配置并输出合并后的配置
+    CheckConfig,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(String, serde_json::Error),
+    Invalid(Vec<String>),
+    Resource(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "failed to read config {}: {}", path.display(), e),
+            ConfigError::Parse(source, e) => write!(f, "invalid config in {}: {}", source, e),
+            ConfigError::Resource(path) => write!(f, "failed to load resource from {}", path),
+            ConfigError::Invalid(errors) => {
+                write!(f, "invalid config:")?;
+                for e in errors {
+                    write!(f, "\n  {}", e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 按配置文件、环境变量、命令行参数的顺序合并配置，后者优先
+pub fn load(args: &Args) -> Result<Config, ConfigError> {
+    let path = path(args);
+    let s = read(&path, args.config.is_none())?;
+    let source = path.display().to_string();
+    let config: Config = serde_json::from_slice(&s).map_err(|e| ConfigError::Parse(source.clone(), e))?;
+    // 转回json后再覆盖，省略的字段也可以通过环境变量设置
+    let mut value = serde_json::to_value(&config).map_err(|e| ConfigError::Parse(source, e))?;
+    apply_env(&mut value, std::env::vars())?;
+    apply_args(&mut value, args);
+    let mut config: Config =
+        serde_json::from_value(value).map_err(|e| ConfigError::Parse("overrides".to_string(), e))?;
+    config.validate().map_err(ConfigError::Invalid)?;
+    for key in &mut config.auth.keys {
+        key.hash = crate::auth::hash(&key.key);
+    }
+    Ok(config)
+}
+
+pub fn path(args: &Args) -> PathBuf {
+    args.config.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH))
+}
+
+/// 只有使用默认路径时才会写入默认配置
+fn read(path: &Path, create: bool) -> Result<Vec<u8>, ConfigError> {
+    if create && !path.exists() {
+        let default_config = include_str!("../server_config.json");
+        fs::write(path, default_config).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        eprintln!("wrote default config to {}", path.display());
+    }
+    fs::read(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))
+}
+
+/// MAA_<段>_<字段>覆盖对应的字段，字符串字段直接使用环境变量的值，其余按json解析，
+/// 默认为null的字段不是合法的json时当作字符串
+fn apply_env(value: &mut Value, vars: impl Iterator<Item = (String, String)>) -> Result<(), ConfigError> {
+    let sections = match value.as_object_mut() {
+        Some(sections) => sections,
+        None => return Ok(()),
     };
+    let mut errors = Vec::new();
+    for (name, v) in vars {
+        let key = match name.strip_prefix(ENV_PREFIX) {
+            Some(key) => key.to_lowercase(),
+            None => continue,
+        };
+        let field = sections
+            .iter_mut()
+            .filter_map(|(section, fields)| {
+                let field = key.strip_prefix(section.as_str())?.strip_prefix('_')?;
+                fields.as_object_mut()?.get_mut(field)
+            })
+            .next();
+        let field = match field {
+            Some(field) => field,
+            None => continue,
+        };
+        match field {
+            Value::String(_) => *field = Value::String(v),
+            Value::Null => *field = serde_json::from_str(&v).unwrap_or(Value::String(v)),
+            _ => match serde_json::from_str(&v) {
+                Ok(parsed) => *field = parsed,
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            },
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ConfigError::Invalid(errors));
+    }
+    Ok(())
+}
+
+fn apply_args(value: &mut Value, args: &Args) {
+    if let Some(address) = &args.address {
+        value["server"]["address"] = address.clone().into();
+    }
+    if let Some(port) = args.port {
+        value["server"]["port"] = port.into();
+    }
+    if let Some(path) = &args.database_path {
+        value["database"]["path"] = path.clone().into();
+    }
+    if let Some(path) = &args.resource_path {
+        value["resource"]["path"] = path.clone().into();
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -26,6 +189,114 @@ pub struct Config {
 
     #[serde(rename = "resource")]
     pub resource: Resource,
+
+    #[serde(rename = "core", default)]
+    pub core: Core,
+
+    #[serde(rename = "screenshot", default)]
+    pub screenshot: Screenshot,
+
+    /// 配置文件中定义的定时计划，不能通过接口修改或删除
+    #[serde(rename = "schedules", default)]
+    pub schedules: Vec<Schedule>,
+
+    #[serde(rename = "supervisor", default)]
+    pub supervisor: Supervisor,
+
+    #[serde(rename = "watchdog", default)]
+    pub watchdog: Watchdog,
+
+    #[serde(rename = "auth", default)]
+    pub auth: Auth,
+
+    #[serde(rename = "log", default)]
+    pub log: Log,
+}
+
+impl Config {
+    /// 用于输出的配置，密钥替换为***
+    pub fn redacted(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(keys) = value["auth"]["keys"].as_array_mut() {
+            for key in keys {
+                key["key"] = "***".into();
+            }
+        }
+        value
+    }
+    /// 返回所有不合法的字段
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.server.address.is_empty() {
+            errors.push("server.address: must not be empty".to_string());
+        }
+        if self.server.port == 0 {
+            errors.push("server.port: must not be 0".to_string());
+        }
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.log.level) {
+            errors.push(format!("log.level: {}", e));
+        }
+        if let Some(tls) = &self.server.tls {
+            if let Err(e) = crate::tls::server_config(tls) {
+                errors.push(format!("server.tls: {}", e));
+            }
+        }
+        if let Some(dir) = &self.core.user_dir {
+            if dir.is_empty() {
+                errors.push("core.user_dir: must not be empty".to_string());
+            }
+        }
+        for name in self.core.static_options.keys() {
+            if Core::static_option_key(name).is_none() {
+                errors.push(format!("core.static_options: unknown option {}", name));
+            }
+        }
+        if self.database.path.is_empty() {
+            errors.push("database.path: must not be empty".to_string());
+        }
+        if self.database.queue_capacity == 0 {
+            errors.push("database.queue_capacity: must be greater than 0".to_string());
+        }
+        if self.database.batch_size == 0 {
+            errors.push("database.batch_size: must be greater than 0".to_string());
+        }
+        if let Some(level) = self.database.compression_level {
+            if !zstd::compression_level_range().contains(&level) {
+                errors.push(format!(
+                    "database.compression_level: {} is out of range {:?}",
+                    level,
+                    zstd::compression_level_range()
+                ));
+            }
+        }
+        if self.resource.path.is_empty() {
+            errors.push("resource.path: must not be empty".to_string());
+        }
+        if self.supervisor.initial_delay == 0 || self.supervisor.max_delay < self.supervisor.initial_delay {
+            errors.push("supervisor: initial_delay must be greater than 0 and not greater than max_delay".to_string());
+        }
+        for (i, schedule) in self.schedules.iter().enumerate() {
+            if let Err(e) = crate::scheduler::validate(schedule) {
+                errors.push(format!("schedules[{}] ({}): {}", i, schedule.name, e));
+            }
+            if self.schedules[..i].iter().any(|x| x.name == schedule.name) {
+                errors.push(format!("schedules[{}] ({}): duplicate name", i, schedule.name));
+            }
+        }
+        for (i, key) in self.auth.keys.iter().enumerate() {
+            if key.name.is_empty() || key.key.is_empty() {
+                errors.push(format!("auth.keys[{}]: name and key must not be empty", i));
+            }
+            if self.auth.keys[..i].iter().any(|x| x.name == key.name) {
+                errors.push(format!("auth.keys[{}] ({}): duplicate name", i, key.name));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -35,6 +306,26 @@ pub struct Database {
 
     #[serde(rename = "drop_on_start_up")]
     pub drop_on_start_up: bool,
+
+    /// 回调消息写入队列的容量，队列满时新消息会被丢弃
+    #[serde(rename = "queue_capacity", default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+
+    /// 每批最多写入的消息数
+    #[serde(rename = "batch_size", default = "default_batch_size")]
+    pub batch_size: usize,
+
+    /// 消息正文的zstd压缩等级，为null时不压缩
+    #[serde(rename = "compression_level", default)]
+    pub compression_level: Option<i32>,
+}
+
+fn default_queue_capacity() -> usize {
+    4096
+}
+
+fn default_batch_size() -> usize {
+    256
 }
 
 #[derive(Serialize, Deserialize)]
@@ -44,6 +335,45 @@ pub struct Server {
 
     #[serde(rename = "port")]
     pub port: u16,
+
+    /// 配置文件修改后自动重新加载
+    #[serde(rename = "watch_config", default = "default_enabled")]
+    pub watch_config: bool,
+
+    /// 关闭服务时等待实例停止和消息写入的毫秒数
+    #[serde(rename = "shutdown_timeout", default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+
+    /// 为null时使用http
+    #[serde(rename = "tls", default)]
+    pub tls: Option<Tls>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tls {
+    /// PEM格式的证书链
+    #[serde(rename = "cert")]
+    pub cert: String,
+
+    /// PEM格式的私钥，支持PKCS#8、RSA和EC
+    #[serde(rename = "key")]
+    pub key: String,
+
+    /// 最低的TLS版本，"1.2"或"1.3"
+    #[serde(rename = "min_version", default = "default_tls_version")]
+    pub min_version: String,
+
+    /// 用于验证客户端证书的CA证书，为null时不要求客户端证书
+    #[serde(rename = "client_ca", default)]
+    pub client_ca: Option<String>,
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30 * 1000
+}
+
+fn default_tls_version() -> String {
+    "1.2".to_string()
 }
 
 #[derive(Serialize, Deserialize)]
@@ -51,3 +381,374 @@ pub struct Resource {
     #[serde(rename = "path")]
     pub path: String,
 }
+
+/// 加载资源前对MaaCore的设置，修改后需要重启
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Core {
+    /// MaaCore写入日志和缓存的目录，默认为启动时的工作目录
+    #[serde(rename = "user_dir")]
+    pub user_dir: Option<String>,
+
+    /// 静态选项，键为cpu_ocr、gpu_ocr或选项的编号
+    #[serde(rename = "static_options")]
+    pub static_options: BTreeMap<String, String>,
+}
+
+impl Core {
+    pub fn static_option_key(name: &str) -> Option<AsstStaticOptionKey> {
+        match name {
+            "cpu_ocr" => Some(1),
+            "gpu_ocr" => Some(2),
+            _ => name.parse().ok().filter(|x| *x > 0),
+        }
+    }
+}
+
+/// 出错时自动截图
+#[derive(Serialize, Deserialize)]
+pub struct Screenshot {
+    #[serde(rename = "enabled")]
+    pub enabled: bool,
+
+    /// 触发截图的消息类型，如TaskChainError、SubTaskError
+    #[serde(rename = "msg_types")]
+    pub msg_types: Vec<String>,
+
+    /// 所有截图占用的最大字节数，超出时删除最早的截图
+    #[serde(rename = "max_total_size")]
+    pub max_total_size: u64,
+}
+
+impl Default for Screenshot {
+    fn default() -> Self {
+        Screenshot {
+            enabled: false,
+            msg_types: vec!["TaskChainError".to_string(), "SubTaskError".to_string()],
+            max_total_size: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// 连接断开时自动重连
+#[derive(Serialize, Deserialize)]
+pub struct Supervisor {
+    #[serde(rename = "enabled")]
+    pub enabled: bool,
+
+    /// 触发重连的ConnectionInfo消息的what字段
+    #[serde(rename = "triggers")]
+    pub triggers: Vec<String>,
+
+    /// 第一次重连前等待的毫秒数，之后每次失败加倍
+    #[serde(rename = "initial_delay")]
+    pub initial_delay: u64,
+
+    #[serde(rename = "max_delay")]
+    pub max_delay: u64,
+
+    /// 连续失败多少次后放弃，为0时不限次数
+    #[serde(rename = "max_attempts")]
+    pub max_attempts: u32,
+
+    /// 重连成功后重新运行断开时未完成的任务
+    #[serde(rename = "restart_tasks")]
+    pub restart_tasks: bool,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Supervisor {
+            enabled: false,
+            triggers: vec!["Disconnect".to_string(), "ScreencapFailed".to_string()],
+            initial_delay: 5 * 1000,
+            max_delay: 5 * 60 * 1000,
+            max_attempts: 10,
+            restart_tasks: false,
+        }
+    }
+}
+
+/// 运行卡住或超时时停止运行，可以通过接口为每个实例单独设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Watchdog {
+    #[serde(rename = "enabled")]
+    pub enabled: bool,
+
+    /// 超过该毫秒数没有收到回调时停止，为0时不检查
+    #[serde(rename = "inactivity_timeout")]
+    pub inactivity_timeout: u64,
+
+    /// 一次运行的最长毫秒数，为0时不限制
+    #[serde(rename = "max_duration")]
+    pub max_duration: u64,
+
+    /// 同一个子任务连续开始的最大次数，为0时不检查
+    #[serde(rename = "max_repeats")]
+    pub max_repeats: u32,
+
+    /// 停止后返回主界面
+    #[serde(rename = "back_to_home")]
+    pub back_to_home: bool,
+
+    /// 停止后重新运行未完成的任务
+    #[serde(rename = "restart")]
+    pub restart: bool,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Watchdog {
+            enabled: false,
+            inactivity_timeout: 20 * 60 * 1000,
+            max_duration: 0,
+            max_repeats: 50,
+            back_to_home: false,
+            restart: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Log {
+    /// 日志级别，也可以按模块设置，如"info,actix_web=warn"
+    #[serde(rename = "level")]
+    pub level: String,
+
+    #[serde(rename = "format")]
+    pub format: LogFormat,
+
+    /// 除标准输出外再写入按时间滚动的日志文件
+    #[serde(rename = "file")]
+    pub file: Option<LogFile>,
+
+    /// 同时通过AsstLog写入MaaCore的日志
+    #[serde(rename = "forward_to_core")]
+    pub forward_to_core: bool,
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Log {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+            file: None,
+            forward_to_core: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LogFile {
+    #[serde(rename = "directory")]
+    pub directory: String,
+
+    /// 文件名的前缀，之后是日期
+    #[serde(rename = "prefix", default = "default_log_prefix")]
+    pub prefix: String,
+
+    #[serde(rename = "rotation", default)]
+    pub rotation: Rotation,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+fn default_log_prefix() -> String {
+    "maa_server.log".to_string()
+}
+
+/// 接口认证，没有任何密钥时不需要认证
+#[derive(Default, Serialize, Deserialize)]
+pub struct Auth {
+    /// 也可以通过/auth/key/create添加，添加的密钥保存在数据库中
+    #[serde(rename = "keys", default)]
+    pub keys: Vec<ApiKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    #[serde(rename = "name")]
+    pub name: String,
+
+    /// 请求时放在Authorization: Bearer之后
+    #[serde(rename = "key")]
+    pub key: String,
+
+    /// 加载配置时计算的哈希，认证时不用每次重新计算
+    #[serde(skip)]
+    pub hash: String,
+
+    #[serde(rename = "scope")]
+    pub scope: Scope,
+
+    /// 只能访问这些实例，为null时不限制
+    #[serde(rename = "instances", default)]
+    pub instances: Option<Vec<i64>>,
+}
+
+/// 权限范围，高的包含低的
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// 只读
+    Read,
+    /// 任务、运行和设备操作
+    Control,
+    /// 实例、消息、配置和密钥管理
+    Admin,
+}
+
+/// 定时运行的计划
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(rename = "name")]
+    pub name: String,
+
+    /// cron表达式，可以省略秒，如"0 4 * * *"
+    #[serde(rename = "cron")]
+    pub cron: String,
+
+    /// 时区名称，如"Asia/Shanghai"
+    #[serde(rename = "timezone", default = "default_timezone")]
+    pub timezone: String,
+
+    /// 在已有的实例上运行
+    #[serde(rename = "instance", default)]
+    pub instance: Option<i64>,
+
+    /// 没有指定实例时，创建实例并按该配置连接
+    #[serde(rename = "connection", default)]
+    pub connection: Option<Connection>,
+
+    /// 按顺序添加的任务
+    #[serde(rename = "tasks")]
+    pub tasks: Vec<TaskSpec>,
+
+    #[serde(rename = "on_busy", default)]
+    pub on_busy: OnBusy,
+
+    #[serde(rename = "enabled", default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Connection {
+    #[serde(rename = "adb_path")]
+    pub adb_path: String,
+
+    #[serde(rename = "target")]
+    pub target: String,
+
+    #[serde(rename = "config", default)]
+    pub config: Value,
+}
+
+/// 一个任务的类型和参数，用于定时计划和预设
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSpec {
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    #[serde(rename = "params", default)]
+    pub params: Value,
+}
+
+/// 到点时实例仍在运行的处理方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnBusy {
+    /// 跳过这次运行
+    #[default]
+    Skip,
+    /// 等实例空闲后再运行
+    Queue,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn config() -> Value {
+        json!({
+            "server": {"address": "127.0.0.1", "port": 11451},
+            "database": {"path": "./db", "drop_on_start_up": true, "compression_level": null},
+            "core": {"user_dir": null},
+        })
+    }
+
+    #[test]
+    fn apply_env_overrides_fields() {
+        let mut value = config();
+        let env = vars(&[
+            ("MAA_SERVER_ADDRESS", "0.0.0.0"),
+            ("MAA_SERVER_PORT", "8080"),
+            ("MAA_DATABASE_DROP_ON_START_UP", "false"),
+            ("PATH", "/usr/bin"),
+        ]);
+        apply_env(&mut value, env).unwrap();
+        assert_eq!(value["server"]["address"], json!("0.0.0.0"));
+        assert_eq!(value["server"]["port"], json!(8080));
+        assert_eq!(value["database"]["drop_on_start_up"], json!(false));
+    }
+
+    #[test]
+    fn apply_env_takes_raw_strings_for_null_fields() {
+        let mut value = config();
+        let env = vars(&[("MAA_CORE_USER_DIR", "/var/lib/maa"), ("MAA_DATABASE_COMPRESSION_LEVEL", "3")]);
+        apply_env(&mut value, env).unwrap();
+        assert_eq!(value["core"]["user_dir"], json!("/var/lib/maa"));
+        assert_eq!(value["database"]["compression_level"], json!(3));
+    }
+
+    #[test]
+    fn apply_env_ignores_unknown_fields() {
+        let mut value = config();
+        apply_env(&mut value, vars(&[("MAA_CONFIG", "./other.json"), ("MAA_SERVER_UNKNOWN", "1")])).unwrap();
+        assert_eq!(value, config());
+    }
+
+    #[test]
+    fn apply_env_reports_invalid_json() {
+        let mut value = config();
+        match apply_env(&mut value, vars(&[("MAA_SERVER_PORT", "port")])) {
+            Err(ConfigError::Invalid(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert!(errors[0].starts_with("MAA_SERVER_PORT"));
+            }
+            _ => panic!("expected an invalid config error"),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use crate::config::{self, Scope};
+use super::Error;
+lazy_static! {
+    /// 以密钥名为键
+    static ref AUTH_DB: Result<sled::Db, sled::Error> = {
+        let mut p = PathBuf::new();
+        p.push(config::current().database.path.clone());
+        p.push("auth");
+        sled::open(p.as_os_str())
+    };
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    AUTH_DB.as_ref().map_err(|e| Error::Sled(e.clone()))
+}
+
+/// 通过接口添加的密钥，只保存哈希
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredKey {
+    pub name: String,
+    /// sha256的十六进制
+    pub hash: String,
+    pub scope: Scope,
+    pub instances: Option<Vec<i64>>,
+    pub created: i64,
+}
+
+pub fn save(key: &StoredKey) -> Result<(), Error> {
+    db()?.insert(key.name.as_bytes(), serde_json::to_vec(key)?)?;
+    Ok(())
+}
+
+pub fn all() -> Result<Vec<StoredKey>, Error> {
+    let mut result = Vec::new();
+    for item in db()?.iter() {
+        let (_, v) = item?;
+        result.push(serde_json::from_slice(&v)?);
+    }
+    Ok(result)
+}
+
+pub fn remove(name: &str) -> Result<bool, Error> {
+    Ok(db()?.remove(name.as_bytes())?.is_some())
+}
+
+pub fn flush() -> Result<(), Error> {
+    db()?.flush()?;
+    Ok(())
+}
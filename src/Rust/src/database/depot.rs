@@ -0,0 +1,89 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use crate::{maa_sys::AsstMsg, resource};
+use super::{snapshot::{Snapshot, Snapshots}, Error};
+
+/// 物品id到数量的映射
+pub type Depot = BTreeMap<String, i64>;
+
+lazy_static! {
+    static ref DEPOT_DB: Snapshots<Depot> = Snapshots::open("depot");
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    DEPOT_DB.db()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemDiff {
+    pub id: String,
+    pub name: Option<String>,
+    pub before: i64,
+    pub after: i64,
+    pub change: i64,
+}
+
+pub fn on_callback(instance: i64, uuid: &str, type_: AsstMsg, value: &Value) -> Result<(), Error> {
+    if type_ != AsstMsg::SubTaskExtraInfo
+        || value.get("what").and_then(Value::as_str) != Some("DepotInfo")
+    {
+        return Ok(());
+    }
+    let details = match value.get("details") {
+        Some(details) => details,
+        None => return Ok(()),
+    };
+    // 识别过程中也会回调，只保存最终结果
+    if details.get("done").and_then(Value::as_bool) == Some(false) {
+        return Ok(());
+    }
+    let items = match details.pointer("/lolicon/object").and_then(Value::as_object) {
+        Some(items) => items,
+        None => return Ok(()),
+    };
+    let depot: Depot = items
+        .iter()
+        .filter_map(|(id, have)| Some((id.clone(), have.as_i64()?)))
+        .collect();
+    DEPOT_DB.insert(uuid, instance, depot)?;
+    Ok(())
+}
+
+pub fn get(uuid: &str, id: u64) -> Result<Option<Snapshot<Depot>>, Error> {
+    DEPOT_DB.get(uuid, id)
+}
+
+pub fn latest(uuid: &str) -> Result<Option<Snapshot<Depot>>, Error> {
+    DEPOT_DB.latest(uuid)
+}
+
+pub fn list(uuid: &str, nums: usize) -> Result<Vec<Snapshot<Depot>>, Error> {
+    DEPOT_DB.list(uuid, nums)
+}
+
+/// 只返回数量有变化的物品
+pub fn diff(from: &Depot, to: &Depot) -> Vec<ItemDiff> {
+    let ids: BTreeSet<&String> = from.keys().chain(to.keys()).collect();
+    ids.into_iter()
+        .filter_map(|id| {
+            let before = from.get(id).copied().unwrap_or(0);
+            let after = to.get(id).copied().unwrap_or(0);
+            if before == after {
+                return None;
+            }
+            Some(ItemDiff {
+                id: id.clone(),
+                name: resource::item_name(id),
+                before,
+                after,
+                change: after - before,
+            })
+        })
+        .collect()
+}
+
+pub fn flush() -> Result<(), Error> {
+    DEPOT_DB.flush()
+}
@@ -0,0 +1,128 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use crate::config::TaskSpec;
+use crate::config;
+use super::Error;
+lazy_static! {
+    static ref FLOW_DB: Result<sled::Db, sled::Error> = {
+        let mut p = PathBuf::new();
+        p.push(config::current().database.path.clone());
+        p.push("flow");
+        sled::open(p.as_os_str())
+    };
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    FLOW_DB.as_ref().map_err(|e| Error::Sled(e.clone()))
+}
+
+/// 流程中的一步
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default)]
+    pub params: Value,
+    /// 只有该步骤完成时才运行
+    #[serde(default)]
+    pub run_if: Option<String>,
+    /// TaskChainError时最多重试的次数
+    #[serde(default)]
+    pub retries: u32,
+    /// 重试后仍失败时运行的任务
+    #[serde(default)]
+    pub fallback: Option<TaskSpec>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    /// 正在运行fallback
+    Fallback,
+    Completed,
+    /// 失败后fallback完成
+    FallbackCompleted,
+    Errored,
+    Skipped,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    #[serde(flatten)]
+    pub spec: StepSpec,
+    pub status: StepStatus,
+    /// 已重试的次数
+    pub attempts: u32,
+    pub task_id: Option<i32>,
+    /// 每次运行(包括重试和fallback)的运行记录id
+    pub run_ids: Vec<u64>,
+    pub started: Option<i64>,
+    pub finished: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowStatus {
+    Running,
+    Completed,
+    /// 有步骤最终失败
+    Failed,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Flow {
+    pub id: u64,
+    pub instance: i64,
+    pub status: FlowStatus,
+    pub steps: Vec<Step>,
+    /// 当前步骤的序号
+    pub current: Option<usize>,
+    pub start: i64,
+    pub end: Option<i64>,
+    pub reason: Option<String>,
+}
+
+pub fn generate_id() -> Result<u64, Error> {
+    Ok(db()?.generate_id()?)
+}
+
+pub fn save(flow: &Flow) -> Result<(), Error> {
+    db()?.insert(flow.id.to_be_bytes(), serde_json::to_vec(flow)?)?;
+    Ok(())
+}
+
+pub fn get(id: u64) -> Result<Option<Flow>, Error> {
+    match db()?.get(id.to_be_bytes())? {
+        Some(v) => Ok(Some(serde_json::from_slice(&v)?)),
+        None => Ok(None),
+    }
+}
+
+/// 按时间倒序返回最多nums个流程
+pub fn list(instance: Option<i64>, nums: usize) -> Result<Vec<Flow>, Error> {
+    let mut result = Vec::new();
+    for item in db()?.iter().rev() {
+        if result.len() >= nums {
+            break;
+        }
+        let (_, v) = item?;
+        let flow: Flow = serde_json::from_slice(&v)?;
+        if instance.is_none_or(|instance| instance == flow.instance) {
+            result.push(flow);
+        }
+    }
+    Ok(result)
+}
+
+pub fn flush() -> Result<(), Error> {
+    db()?.flush()?;
+    Ok(())
+}
@@ -1 +1,129 @@
-pub mod msg;
\ No newline at end of file
+pub mod msg;
+pub mod writer;
+pub mod recruit;
+pub mod snapshot;
+pub mod depot;
+pub mod operbox;
+pub mod run;
+pub mod screenshot;
+pub mod schedule;
+pub mod preset;
+pub mod flow;
+pub mod auth;
+
+/// 退出前把所有数据库写入磁盘，出错时继续写入其余的数据库
+pub fn flush_all() {
+    type Flush = fn() -> Result<(), Error>;
+    let flushes: [(&str, Flush); 10] = [
+        ("message", msg::flush),
+        ("run", run::flush),
+        ("recruit", recruit::flush),
+        ("depot", depot::flush),
+        ("operbox", operbox::flush),
+        ("screenshot", screenshot::flush),
+        ("schedule", schedule::flush),
+        ("preset", preset::flush),
+        ("flow", flow::flush),
+        ("auth", auth::flush),
+    ];
+    for (name, flush) in flushes {
+        if let Err(e) = flush() {
+            tracing::warn!("failed to flush {} database: {:?}", name, e);
+        }
+    }
+}
+
+/// 检查所有数据库是否都已打开，返回打开失败的数据库
+pub fn check() -> Result<(), String> {
+    type Db = fn() -> Result<&'static sled::Db, Error>;
+    let dbs: [(&str, Db); 10] = [
+        ("message", msg::db),
+        ("run", run::db),
+        ("recruit", recruit::db),
+        ("depot", depot::db),
+        ("operbox", operbox::db),
+        ("screenshot", screenshot::db),
+        ("schedule", schedule::db),
+        ("preset", preset::db),
+        ("flow", flow::db),
+        ("auth", auth::db),
+    ];
+    let failed: Vec<String> = dbs
+        .iter()
+        .filter_map(|(name, db)| db().err().map(|e| format!("{}: {:?}", name, e)))
+        .collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(failed.join("; "))
+    }
+}
+
+/// 已有的树，不存在时不创建，避免读取时留下空树
+fn existing_tree(db: &sled::Db, name: &str) -> Result<Option<sled::Tree>, Error> {
+    if !db.tree_names().iter().any(|x| x == name.as_bytes()) {
+        return Ok(None);
+    }
+    Ok(Some(db.open_tree(name)?))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Sled(sled::Error),
+    SerdeJson(serde_json::Error),
+    IVecNotLongEnough,
+    InvalidUtf8String,
+    Bincode(bincode::Error),
+    Io(std::io::Error),
+    /// 记录的格式版本不受支持
+    UnsupportedFormat(u8),
+    /// 数据库由更新版本的程序创建
+    UnsupportedSchema(u32),
+    /// 名称保留给内部使用
+    ReservedName,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Sled(e) => write!(f, "sled: {}", e),
+            Error::SerdeJson(e) => write!(f, "json: {}", e),
+            Error::IVecNotLongEnough => write!(f, "record is too short"),
+            Error::InvalidUtf8String => write!(f, "record is not valid utf-8"),
+            Error::Bincode(e) => write!(f, "bincode: {}", e),
+            Error::Io(e) => write!(f, "io: {}", e),
+            Error::ReservedName => write!(f, "name is reserved"),
+            Error::UnsupportedFormat(format) => write!(f, "unsupported record format {}", format),
+            Error::UnsupportedSchema(version) => write!(
+                f,
+                "database schema version {} is newer than supported version {}",
+                version,
+                msg::SCHEMA_VERSION
+            ),
+        }
+    }
+}
+
+impl From<sled::Error> for Error {
+    fn from(e: sled::Error) -> Self {
+        Self::Sled(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::SerdeJson(e)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Self::Bincode(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
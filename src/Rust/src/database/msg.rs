@@ -1,45 +1,99 @@
 use lazy_static::lazy_static;
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
-use crate::CONFIG;
+use crate::config;
+use super::{writer, Error};
 lazy_static! {
-    static ref MSG_DB: sled::Db = {
+    /// 打开失败时保留错误，由/readyz报告，不在第一次使用时panic
+    static ref MSG_DB: Result<sled::Db, sled::Error> = {
         let mut p = PathBuf::new();
-        p.push(CONFIG.database.path.clone());
+        p.push(config::current().database.path.clone());
         p.push("message");
-        sled::open(p.as_os_str()).unwrap()
+        sled::open(p.as_os_str())
     };
 }
 
-#[derive(Debug)]
-pub enum Error {
-    Sled(sled::Error),
-    SerdeJson(serde_json::Error),
-    IVecNotLongEnough,
-    InvalidUtf8String,
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    MSG_DB.as_ref().map_err(|e| Error::Sled(e.clone()))
 }
 
-impl From<sled::Error> for Error {
-    fn from(e: sled::Error) -> Self {
-        Self::Sled(e)
-    }
+/// 写入并删除一条记录，检查数据库是否可写
+pub fn probe() -> Result<(), Error> {
+    let db = db()?;
+    db.insert(PROBE_KEY, &[])?;
+    db.remove(PROBE_KEY)?;
+    db.flush()?;
+    Ok(())
 }
 
-impl From<serde_json::Error> for Error {
-    fn from(e: serde_json::Error) -> Self {
-        Self::SerdeJson(e)
-    }
-}
+/// 消息记录的格式版本，存放在记录的第一个字节。
+/// 旧格式以8字节的大端时间戳开头，第一个字节总是0
+const FORMAT_LEGACY: u8 = 0;
+const FORMAT_V1: u8 = 1;
+/// 第二个字节的标志位
+const FLAG_ZSTD: u8 = 1;
+/// 正文超过该长度时才压缩
+const COMPRESS_MIN_SIZE: usize = 256;
+
+/// 数据库结构的版本，保存在默认树中
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+pub(super) const SCHEMA_VERSION: u32 = 2;
+/// 迁移时每批转换的消息条数
+const MIGRATE_BATCH_SIZE: usize = 1000;
+/// 检查是否可写时使用的键
+const PROBE_KEY: &str = "__probe";
+
+/// 服务器自身产生的消息类型，与maa的消息类型不重叠
+pub const WATCHDOG_TRIPPED: u32 = 30000;
+
 #[derive(Debug)]
 pub struct Msg {
     pub time: i64,
     pub type_: u32,
     pub uuid: String,
     pub body: String,
+    pub instance: Option<i64>,
+    pub task_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordV1 {
+    time: i64,
+    type_: u32,
+    instance: Option<i64>,
+    task_id: Option<i32>,
+    body: Vec<u8>,
 }
 
 impl Msg {
     fn from_ivec(uuid: &str, ivec: &sled::IVec) -> Result<Self, Error> {
+        match ivec.first() {
+            Some(&FORMAT_LEGACY) => Self::from_legacy_ivec(uuid, ivec),
+            Some(&FORMAT_V1) => {
+                if ivec.len() < 2 {
+                    return Err(Error::IVecNotLongEnough);
+                }
+                let record: RecordV1 = bincode::deserialize(&ivec[2..])?;
+                let body = if ivec[1] & FLAG_ZSTD != 0 {
+                    zstd::decode_all(record.body.as_slice())?
+                } else {
+                    record.body
+                };
+                Ok(Msg {
+                    time: record.time,
+                    type_: record.type_,
+                    uuid: uuid.to_string(),
+                    body: String::from_utf8(body).map_err(|_| Error::InvalidUtf8String)?,
+                    instance: record.instance,
+                    task_id: record.task_id,
+                })
+            }
+            Some(&format) => Err(Error::UnsupportedFormat(format)),
+            None => Err(Error::IVecNotLongEnough),
+        }
+    }
+    fn from_legacy_ivec(uuid: &str, ivec: &sled::IVec) -> Result<Self, Error> {
         if ivec.len() <= 12 {
             return Err(Error::IVecNotLongEnough);
         };
@@ -59,22 +113,109 @@ impl Msg {
             uuid: uuid.to_string(),
             type_,
             body,
+            instance: None,
+            task_id: None,
         };
         Ok(msg)
     }
+    fn to_ivec(&self) -> Result<Vec<u8>, Error> {
+        self.encode(config::current().database.compression_level)
+    }
+    fn encode(&self, compression_level: Option<i32>) -> Result<Vec<u8>, Error> {
+        let mut flags = 0;
+        let body = match compression_level {
+            Some(level) if self.body.len() > COMPRESS_MIN_SIZE => {
+                flags |= FLAG_ZSTD;
+                zstd::encode_all(self.body.as_bytes(), level)?
+            }
+            _ => self.body.as_bytes().to_vec(),
+        };
+        let record = RecordV1 {
+            time: self.time,
+            type_: self.type_,
+            instance: self.instance,
+            task_id: self.task_id,
+            body,
+        };
+        let mut value = vec![FORMAT_V1, flags];
+        bincode::serialize_into(&mut value, &record)?;
+        Ok(value)
+    }
 }
+#[allow(dead_code)]
 pub fn insert_msg(msg: &Msg) -> Result<u64, Error> {
-    let uuid_tree = MSG_DB.open_tree(&msg.uuid)?;
-    let id = MSG_DB.generate_id()?;
-    let mut value = msg.time.to_be_bytes().to_vec();
-    value.extend_from_slice(&msg.type_.to_be_bytes());
-    value.extend_from_slice(msg.body.as_bytes());
-    uuid_tree.insert(id.to_be_bytes(), value)?;
+    let uuid_tree = db()?.open_tree(&msg.uuid)?;
+    let id = db()?.generate_id()?;
+    uuid_tree.insert(id.to_be_bytes(), msg.to_ivec()?)?;
     Ok(id)
 }
+/// 按uuid分组批量写入，返回与msgs一一对应的id
+pub fn insert_batch<'a>(msgs: impl Iterator<Item = &'a Msg>) -> Result<Vec<u64>, Error> {
+    let mut ids = Vec::new();
+    let mut batches: HashMap<&str, sled::Batch> = HashMap::new();
+    for msg in msgs {
+        let id = db()?.generate_id()?;
+        batches
+            .entry(&msg.uuid)
+            .or_default()
+            .insert(&id.to_be_bytes(), msg.to_ivec()?);
+        ids.push(id);
+    }
+    for (uuid, batch) in batches {
+        db()?.open_tree(uuid)?.apply_batch(batch)?;
+    }
+    Ok(ids)
+}
+pub fn flush() -> Result<(), Error> {
+    db()?.flush()?;
+    Ok(())
+}
+/// 把旧格式的消息改写为当前格式，并记录数据库结构的版本
+pub fn migrate() -> Result<(), Error> {
+    if let Some(migrated) = migrate_db(db()?, config::current().database.compression_level)? {
+        tracing::info!("migrated {} messages to schema version {}", migrated, SCHEMA_VERSION);
+    }
+    Ok(())
+}
+
+/// 返回转换的消息条数，已是当前版本时返回None
+fn migrate_db(db: &sled::Db, compression_level: Option<i32>) -> Result<Option<u64>, Error> {
+    let version = match db.get(SCHEMA_VERSION_KEY)? {
+        Some(v) => u32::from_be_bytes(v.as_ref().try_into().map_err(|_| Error::IVecNotLongEnough)?),
+        None => 1,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(Error::UnsupportedSchema(version));
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(None);
+    }
+    let mut migrated = 0;
+    for uuid in uuids(db)? {
+        let tree = db.open_tree(&uuid)?;
+        let mut batch = sled::Batch::default();
+        let mut size = 0;
+        for item in tree.iter() {
+            let (k, v) = item?;
+            if v.first() == Some(&FORMAT_LEGACY) {
+                batch.insert(k, Msg::from_legacy_ivec(&uuid, &v)?.encode(compression_level)?);
+                migrated += 1;
+                size += 1;
+            }
+            if size >= MIGRATE_BATCH_SIZE {
+                tree.apply_batch(std::mem::take(&mut batch))?;
+                size = 0;
+            }
+        }
+        tree.apply_batch(batch)?;
+    }
+    db.insert(SCHEMA_VERSION_KEY, &SCHEMA_VERSION.to_be_bytes())?;
+    db.flush()?;
+    Ok(Some(migrated))
+}
 #[allow(dead_code)]
 pub fn get_msg(uuid: &str, id: u64) -> Result<Option<Msg>, Error> {
-    let uuid_tree = MSG_DB.open_tree(uuid)?;
+    let uuid_tree = db()?.open_tree(uuid)?;
     match uuid_tree.get(id.to_be_bytes())? {
         Some(value) => {
             let msg = Msg::from_ivec(uuid, &value)?;
@@ -85,8 +226,12 @@ pub fn get_msg(uuid: &str, id: u64) -> Result<Option<Msg>, Error> {
 }
 
 pub fn get_all_uuid() -> Result<Vec<String>, Error> {
+    uuids(db()?)
+}
+
+fn uuids(db: &sled::Db) -> Result<Vec<String>, Error> {
     let mut result = Vec::new();
-    for i in MSG_DB.tree_names() {
+    for i in db.tree_names() {
         let s = String::from_utf8(i.to_vec()).map_err(|_| Error::InvalidUtf8String)?;
         if s == "__sled__default" {
             continue;
@@ -95,8 +240,9 @@ pub fn get_all_uuid() -> Result<Vec<String>, Error> {
     }
     Ok(result)
 }
-pub fn get_last_msg(uuid: &str, nums: usize) -> Result<Vec<Msg>, Error> {
-    let uuid_tree = MSG_DB.open_tree(uuid)?;
+/// 返回(消息id, 消息)，按时间倒序
+pub fn get_last_msg(uuid: &str, nums: usize) -> Result<Vec<(u64, Msg)>, Error> {
+    let uuid_tree = db()?.open_tree(uuid)?;
     if uuid_tree.is_empty(){
         drop(uuid)?;
     }
@@ -104,8 +250,9 @@ pub fn get_last_msg(uuid: &str, nums: usize) -> Result<Vec<Msg>, Error> {
     let mut result = Vec::new();
     for _ in 0..nums {
         match iter.next_back() {
-            Some(Ok((_, v))) => {
-                result.push(Msg::from_ivec(uuid, &v)?);
+            Some(Ok((k, v))) => {
+                let id = u64::from_be_bytes(k.as_ref().try_into().map_err(|_| Error::IVecNotLongEnough)?);
+                result.push((id, Msg::from_ivec(uuid, &v)?));
             }
             _ => break,
         }
@@ -113,17 +260,18 @@ pub fn get_last_msg(uuid: &str, nums: usize) -> Result<Vec<Msg>, Error> {
     return Ok(result);
 }
 pub fn drop(uuid: &str) -> Result<(), Error> {
-    MSG_DB.drop_tree(uuid)?;
+    db()?.drop_tree(uuid)?;
+    super::screenshot::drop(uuid)?;
     Ok(())
 }
 
 pub fn drop_all() -> Result<(), Error> {
     for uuid in get_all_uuid()? {
-        MSG_DB.drop_tree(uuid)?;
+        drop(&uuid)?;
     }
     Ok(())
 }
-#[allow(unused_variables)]
+/// 只把消息放入写入队列，解析和入库都在写入线程中进行，避免阻塞MaaCore的回调线程
 #[allow(unused_must_use)]
 pub unsafe extern "C" fn maa_store_callback(
     msg: std::os::raw::c_int,
@@ -132,22 +280,89 @@ pub unsafe extern "C" fn maa_store_callback(
 ) {
     std::panic::catch_unwind(|| {
         let body = std::ffi::CStr::from_ptr(detail_json)
-            .to_str()
-            .unwrap()
-            .to_string();
-        let now = chrono::Local::now().timestamp_millis();
-        if let Ok(value) = serde_json::from_str::<Value>(&body) {
-            if let Some(uuid) = value.get("uuid") {
-                if let Some(uuid) = uuid.as_str() {
-                    let msg = Msg {
-                        time: now,
-                        type_: msg as u32,
-                        uuid: uuid.to_string(),
-                        body: body,
-                    };
-                    insert_msg(&msg).unwrap();
-                }
-            }
-        }
+            .to_string_lossy()
+            .into_owned();
+        writer::push(writer::Callback {
+            instance: id as i64,
+            type_: msg as u32,
+            time: chrono::Local::now().timestamp_millis(),
+            body,
+        });
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy(time: i64, type_: u32, body: &str) -> sled::IVec {
+        let mut value = time.to_be_bytes().to_vec();
+        value.extend(type_.to_be_bytes());
+        value.extend(body.as_bytes());
+        value.into()
+    }
+
+    fn temporary_db() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    #[test]
+    fn legacy_record_round_trips_through_v1() {
+        let msg = Msg::from_ivec("uuid", &legacy(1700000000000, 10002, r#"{"taskid":1}"#)).unwrap();
+        assert_eq!((msg.time, msg.type_, msg.body.as_str()), (1700000000000, 10002, r#"{"taskid":1}"#));
+        assert_eq!((msg.instance, msg.task_id), (None, None));
+        let encoded = msg.encode(None).unwrap();
+        assert_eq!(&encoded[..2], &[FORMAT_V1, 0]);
+        let decoded = Msg::from_ivec("uuid", &encoded.into()).unwrap();
+        assert_eq!((decoded.time, decoded.type_, decoded.body), (msg.time, msg.type_, msg.body));
+    }
+
+    #[test]
+    fn long_bodies_are_compressed() {
+        let msg = Msg {
+            time: 1,
+            type_: 10001,
+            uuid: "uuid".to_string(),
+            body: "x".repeat(COMPRESS_MIN_SIZE * 4),
+            instance: Some(2),
+            task_id: Some(3),
+        };
+        let encoded = msg.encode(Some(3)).unwrap();
+        assert_eq!(encoded[1] & FLAG_ZSTD, FLAG_ZSTD);
+        assert!(encoded.len() < msg.body.len());
+        let decoded = Msg::from_ivec("uuid", &encoded.into()).unwrap();
+        assert_eq!(decoded.body, msg.body);
+        assert_eq!((decoded.instance, decoded.task_id), (Some(2), Some(3)));
+    }
+
+    #[test]
+    fn unknown_format_is_rejected() {
+        let ivec: sled::IVec = vec![9, 0, 0].into();
+        assert!(matches!(Msg::from_ivec("uuid", &ivec), Err(Error::UnsupportedFormat(9))));
+        assert!(matches!(Msg::from_ivec("uuid", &sled::IVec::default()), Err(Error::IVecNotLongEnough)));
+    }
+
+    #[test]
+    fn migrate_converts_legacy_records_once() {
+        let db = temporary_db();
+        let tree = db.open_tree("uuid").unwrap();
+        tree.insert(1u64.to_be_bytes(), legacy(1, 10001, "{}")).unwrap();
+        tree.insert(2u64.to_be_bytes(), legacy(2, 10002, "{}")).unwrap();
+        assert_eq!(migrate_db(&db, None).unwrap(), Some(2));
+        for item in tree.iter() {
+            let (_, v) = item.unwrap();
+            assert_eq!(v.first(), Some(&FORMAT_V1));
+            assert_eq!(Msg::from_ivec("uuid", &v).unwrap().body, "{}");
+        }
+        let version = db.get(SCHEMA_VERSION_KEY).unwrap().unwrap();
+        assert_eq!(version.as_ref(), SCHEMA_VERSION.to_be_bytes());
+        assert_eq!(migrate_db(&db, None).unwrap(), None);
+    }
+
+    #[test]
+    fn migrate_rejects_newer_schema() {
+        let db = temporary_db();
+        db.insert(SCHEMA_VERSION_KEY, &(SCHEMA_VERSION + 1).to_be_bytes()).unwrap();
+        assert!(matches!(migrate_db(&db, None), Err(Error::UnsupportedSchema(v)) if v == SCHEMA_VERSION + 1));
+    }
+}
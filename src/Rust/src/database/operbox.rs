@@ -0,0 +1,131 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use crate::maa_sys::AsstMsg;
+use super::{snapshot::{Snapshot, Snapshots}, Error};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Oper {
+    pub id: String,
+    pub name: String,
+    pub elite: Option<i64>,
+    pub level: Option<i64>,
+    pub potential: Option<i64>,
+    pub rarity: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperBox {
+    /// 以干员id为键，识别不到id时以干员名为键
+    pub owned: BTreeMap<String, Oper>,
+    pub unowned: Vec<Oper>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OperChange {
+    pub before: Oper,
+    pub after: Oper,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct OperBoxDiff {
+    pub obtained: Vec<Oper>,
+    pub lost: Vec<Oper>,
+    pub changed: Vec<OperChange>,
+}
+
+lazy_static! {
+    static ref OPERBOX_DB: Snapshots<OperBox> = Snapshots::open("operbox");
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    OPERBOX_DB.db()
+}
+
+fn parse_oper(value: &Value) -> Option<Oper> {
+    let field = |key: &str| value.get(key).and_then(Value::as_i64);
+    Some(Oper {
+        id: value.get("id").and_then(Value::as_str).unwrap_or_default().to_string(),
+        name: value.get("name")?.as_str()?.to_string(),
+        elite: field("elite"),
+        level: field("level"),
+        potential: field("potential"),
+        rarity: field("rarity"),
+    })
+}
+
+pub fn on_callback(instance: i64, uuid: &str, type_: AsstMsg, value: &Value) -> Result<(), Error> {
+    if type_ != AsstMsg::SubTaskExtraInfo
+        || value.get("what").and_then(Value::as_str) != Some("OperBoxInfo")
+    {
+        return Ok(());
+    }
+    let details = match value.get("details") {
+        Some(details) => details,
+        None => return Ok(()),
+    };
+    // 识别过程中也会回调，只保存最终结果
+    if details.get("done").and_then(Value::as_bool) == Some(false) {
+        return Ok(());
+    }
+    let opers = |key: &str, own: bool| -> Vec<Oper> {
+        details
+            .get(key)
+            .and_then(Value::as_array)
+            .map(|opers| {
+                opers
+                    .iter()
+                    .filter(|oper| oper.get("own").and_then(Value::as_bool).unwrap_or(true) == own)
+                    .filter_map(parse_oper)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let owned = opers("own_opers", true)
+        .into_iter()
+        .map(|oper| {
+            let key = if oper.id.is_empty() { oper.name.clone() } else { oper.id.clone() };
+            (key, oper)
+        })
+        .collect();
+    let unowned = opers("all_opers", false);
+    OPERBOX_DB.insert(uuid, instance, OperBox { owned, unowned })?;
+    Ok(())
+}
+
+pub fn get(uuid: &str, id: u64) -> Result<Option<Snapshot<OperBox>>, Error> {
+    OPERBOX_DB.get(uuid, id)
+}
+
+pub fn latest(uuid: &str) -> Result<Option<Snapshot<OperBox>>, Error> {
+    OPERBOX_DB.latest(uuid)
+}
+
+pub fn list(uuid: &str, nums: usize) -> Result<Vec<Snapshot<OperBox>>, Error> {
+    OPERBOX_DB.list(uuid, nums)
+}
+
+pub fn diff(from: &OperBox, to: &OperBox) -> OperBoxDiff {
+    let mut result = OperBoxDiff::default();
+    for (key, after) in &to.owned {
+        match from.owned.get(key) {
+            None => result.obtained.push(after.clone()),
+            Some(before) if before != after => result.changed.push(OperChange {
+                before: before.clone(),
+                after: after.clone(),
+            }),
+            _ => {}
+        }
+    }
+    for (key, before) in &from.owned {
+        if !to.owned.contains_key(key) {
+            result.lost.push(before.clone());
+        }
+    }
+    result
+}
+
+pub fn flush() -> Result<(), Error> {
+    OPERBOX_DB.flush()
+}
@@ -0,0 +1,56 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use crate::config::TaskSpec;
+use crate::config;
+use super::Error;
+lazy_static! {
+    /// 以预设名为键
+    static ref PRESET_DB: Result<sled::Db, sled::Error> = {
+        let mut p = PathBuf::new();
+        p.push(config::current().database.path.clone());
+        p.push("preset");
+        sled::open(p.as_os_str())
+    };
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    PRESET_DB.as_ref().map_err(|e| Error::Sled(e.clone()))
+}
+
+/// 按顺序添加的一组任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub tasks: Vec<TaskSpec>,
+}
+
+pub fn save(preset: &Preset) -> Result<(), Error> {
+    db()?.insert(preset.name.as_bytes(), serde_json::to_vec(preset)?)?;
+    Ok(())
+}
+
+pub fn get(name: &str) -> Result<Option<Preset>, Error> {
+    match db()?.get(name.as_bytes())? {
+        Some(v) => Ok(Some(serde_json::from_slice(&v)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn all() -> Result<Vec<Preset>, Error> {
+    let mut result = Vec::new();
+    for item in db()?.iter() {
+        let (_, v) = item?;
+        result.push(serde_json::from_slice(&v)?);
+    }
+    Ok(result)
+}
+
+pub fn remove(name: &str) -> Result<bool, Error> {
+    Ok(db()?.remove(name.as_bytes())?.is_some())
+}
+
+pub fn flush() -> Result<(), Error> {
+    db()?.flush()?;
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use crate::{config, maa_sys::AsstMsg};
+use super::Error;
+lazy_static! {
+    static ref RECRUIT_DB: Result<sled::Db, sled::Error> = {
+        let mut p = PathBuf::new();
+        p.push(config::current().database.path.clone());
+        p.push("recruit");
+        sled::open(p.as_os_str())
+    };
+    static ref PENDING: Mutex<HashMap<i64, Pending>> = Mutex::new(HashMap::new());
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    RECRUIT_DB.as_ref().map_err(|e| Error::Sled(e.clone()))
+}
+
+/// 一次公招的记录，对应一个公招位从识别tag到确认招募的过程
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub time: i64,
+    pub instance: i64,
+    pub uuid: String,
+    pub tags: Vec<String>,
+    pub special_tags: Vec<String>,
+    pub selected_tags: Vec<String>,
+    pub level: Option<i64>,
+    pub refreshed: bool,
+    pub expedited: bool,
+}
+
+#[derive(Default)]
+struct Pending {
+    record: Option<Record>,
+    refreshing: bool,
+    expedite_next: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub uuid: Option<String>,
+    pub instance: Option<i64>,
+    pub begin: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl Filter {
+    fn matches(&self, record: &Record) -> bool {
+        self.uuid.as_ref().is_none_or(|uuid| *uuid == record.uuid)
+            && self.instance.is_none_or(|instance| instance == record.instance)
+            && self.begin.is_none_or(|begin| record.time >= begin)
+            && self.end.is_none_or(|end| record.time < end)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub total: u64,
+    pub refreshed: u64,
+    pub expedited: u64,
+    pub levels: BTreeMap<i64, u64>,
+    pub special_tags: BTreeMap<String, u64>,
+    pub tags: Vec<TagCount>,
+}
+
+fn string_list(details: Option<&Value>, key: &str) -> Vec<String> {
+    details
+        .and_then(|d| d.get(key))
+        .and_then(Value::as_array)
+        .map(|tags| {
+            tags.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn insert_record(record: &Record) -> Result<u64, Error> {
+    let id = db()?.generate_id()?;
+    db()?.insert(id.to_be_bytes(), serde_json::to_vec(record)?)?;
+    Ok(id)
+}
+
+pub fn on_callback(instance: i64, uuid: &str, type_: AsstMsg, value: &Value) -> Result<(), Error> {
+    let mut pending = PENDING.lock().unwrap_or_else(PoisonError::into_inner);
+    let details = value.get("details");
+    match type_ {
+        AsstMsg::SubTaskExtraInfo => {
+            let state = pending.entry(instance).or_default();
+            let what = value.get("what").and_then(Value::as_str).unwrap_or_default();
+            match what {
+                "RecruitTagsDetected" => {
+                    let tags = string_list(details, "tags");
+                    if state.refreshing {
+                        state.refreshing = false;
+                        if let Some(record) = state.record.as_mut() {
+                            record.tags = tags;
+                            record.special_tags.clear();
+                            record.level = None;
+                            return Ok(());
+                        }
+                    }
+                    if let Some(record) = state.record.take() {
+                        insert_record(&record)?;
+                    }
+                    state.record = Some(Record {
+                        time: chrono::Local::now().timestamp_millis(),
+                        instance,
+                        uuid: uuid.to_string(),
+                        tags,
+                        special_tags: Vec::new(),
+                        selected_tags: Vec::new(),
+                        level: None,
+                        refreshed: false,
+                        expedited: std::mem::take(&mut state.expedite_next),
+                    });
+                }
+                "RecruitSpecialTag" => {
+                    let tag = details.and_then(|d| d.get("tag")).and_then(Value::as_str);
+                    if let (Some(record), Some(tag)) = (state.record.as_mut(), tag) {
+                        record.special_tags.push(tag.to_string());
+                    }
+                }
+                "RecruitResult" => {
+                    if let Some(record) = state.record.as_mut() {
+                        record.level = details.and_then(|d| d.get("level")).and_then(Value::as_i64);
+                    }
+                }
+                "RecruitTagsRefreshed" => {
+                    if let Some(record) = state.record.as_mut() {
+                        record.refreshed = true;
+                        state.refreshing = true;
+                    }
+                }
+                "RecruitTagsSelected" => {
+                    if let Some(record) = state.record.as_mut() {
+                        record.selected_tags = string_list(details, "tags");
+                    }
+                }
+                "RecruitSlotCompleted" => {
+                    if let Some(record) = state.record.take() {
+                        insert_record(&record)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        AsstMsg::SubTaskCompleted => {
+            let task = details.and_then(|d| d.get("task")).and_then(Value::as_str);
+            match task {
+                Some("RecruitConfirm") => {
+                    if let Some(record) = pending.get_mut(&instance).and_then(|s| s.record.take()) {
+                        insert_record(&record)?;
+                    }
+                }
+                Some("RecruitNowConfirm") => {
+                    pending.entry(instance).or_default().expedite_next = true;
+                }
+                _ => {}
+            }
+        }
+        AsstMsg::TaskChainCompleted
+        | AsstMsg::TaskChainError
+        | AsstMsg::TaskChainStopped
+        | AsstMsg::AllTasksCompleted => {
+            if let Some(record) = pending.remove(&instance).and_then(|s| s.record) {
+                insert_record(&record)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 按时间倒序返回最多nums条符合条件的记录
+pub fn get_records(filter: &Filter, nums: usize) -> Result<Vec<Record>, Error> {
+    let mut result = Vec::new();
+    for item in db()?.iter().rev() {
+        if result.len() >= nums {
+            break;
+        }
+        let (_, v) = item?;
+        let record: Record = serde_json::from_slice(&v)?;
+        if filter.matches(&record) {
+            result.push(record);
+        }
+    }
+    Ok(result)
+}
+
+pub fn summarize(records: &[Record]) -> Summary {
+    let mut summary = Summary::default();
+    let mut tags: HashMap<&str, u64> = HashMap::new();
+    for record in records {
+        summary.total += 1;
+        summary.refreshed += record.refreshed as u64;
+        summary.expedited += record.expedited as u64;
+        if let Some(level) = record.level {
+            *summary.levels.entry(level).or_default() += 1;
+        }
+        for tag in &record.special_tags {
+            *summary.special_tags.entry(tag.clone()).or_default() += 1;
+        }
+        for tag in &record.tags {
+            *tags.entry(tag).or_default() += 1;
+        }
+    }
+    summary.tags = tags
+        .into_iter()
+        .map(|(tag, count)| TagCount {
+            tag: tag.to_string(),
+            count,
+        })
+        .collect();
+    summary
+        .tags
+        .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+    summary
+}
+
+pub fn flush() -> Result<(), Error> {
+    db()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(tags: &[&str], special_tags: &[&str], level: Option<i64>, refreshed: bool, expedited: bool) -> Record {
+        let strings = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        Record {
+            time: 0,
+            instance: 1,
+            uuid: "uuid".to_string(),
+            tags: strings(tags),
+            special_tags: strings(special_tags),
+            selected_tags: Vec::new(),
+            level,
+            refreshed,
+            expedited,
+        }
+    }
+
+    #[test]
+    fn summarize_counts_records() {
+        let records = [
+            record(&["近战位", "输出"], &[], Some(3), true, false),
+            record(&["高级资深干员", "输出"], &["高级资深干员"], Some(6), false, true),
+            record(&["近战位", "支援"], &[], None, false, false),
+        ];
+        let summary = summarize(&records);
+        assert_eq!((summary.total, summary.refreshed, summary.expedited), (3, 1, 1));
+        assert_eq!(summary.levels, BTreeMap::from([(3, 1), (6, 1)]));
+        assert_eq!(summary.special_tags, BTreeMap::from([("高级资深干员".to_string(), 1)]));
+    }
+
+    #[test]
+    fn summarize_sorts_tags_by_count_then_name() {
+        let records = [
+            record(&["b", "c"], &[], None, false, false),
+            record(&["a", "c"], &[], None, false, false),
+        ];
+        let tags: Vec<(String, u64)> = summarize(&records)
+            .tags
+            .into_iter()
+            .map(|x| (x.tag, x.count))
+            .collect();
+        assert_eq!(tags, [("c".to_string(), 2), ("a".to_string(), 1), ("b".to_string(), 1)]);
+    }
+
+    #[test]
+    fn summarize_empty() {
+        let summary = summarize(&[]);
+        assert_eq!(summary.total, 0);
+        assert!(summary.tags.is_empty() && summary.levels.is_empty());
+    }
+}
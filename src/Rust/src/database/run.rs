@@ -0,0 +1,409 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use crate::{config::{self, TaskSpec}, maa_sys::AsstMsg};
+use super::Error;
+lazy_static! {
+    static ref RUN_DB: Result<sled::Db, sled::Error> = {
+        let mut p = PathBuf::new();
+        p.push(config::current().database.path.clone());
+        p.push("run");
+        sled::open(p.as_os_str())
+    };
+    /// 每个实例正在进行的运行
+    static ref OPEN_RUNS: Mutex<HashMap<i64, Run>> = Mutex::new(HashMap::new());
+    /// 每个实例最近的运行，包括已结束的
+    static ref LATEST_RUNS: Mutex<HashMap<i64, Run>> = Mutex::new(HashMap::new());
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    RUN_DB.as_ref().map_err(|e| Error::Sled(e.clone()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Running,
+    Completed,
+    Errored,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunTask {
+    pub task_id: i32,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub params: Value,
+    pub status: Status,
+    pub started: Option<i64>,
+    pub finished: Option<i64>,
+    pub errors: u64,
+    /// 最近一次出错的信息
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// 从/run/start到全部任务结束(或被中止)的一次运行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    pub id: u64,
+    pub instance: i64,
+    pub uuid: Option<String>,
+    pub start: i64,
+    pub end: Option<i64>,
+    pub duration: Option<i64>,
+    pub status: Status,
+    pub tasks: Vec<RunTask>,
+    pub errors: u64,
+    pub reason: Option<String>,
+}
+
+/// 回调消息对运行的影响
+#[derive(Debug, PartialEq, Eq)]
+enum Change {
+    None,
+    Updated,
+    Finished(Status),
+}
+
+impl Run {
+    fn task_mut(&mut self, value: &Value) -> Option<&mut RunTask> {
+        let task_id = value.get("taskid").and_then(Value::as_i64)?;
+        self.tasks.iter_mut().find(|t| t.task_id as i64 == task_id)
+    }
+    /// 消息中的taskid或finished_tasks是否属于这次运行，没有任务的运行接受不带任务id的消息
+    fn owns(&self, value: &Value) -> bool {
+        let mut ids: Vec<i64> = value
+            .get("finished_tasks")
+            .and_then(Value::as_array)
+            .map(|x| x.iter().filter_map(Value::as_i64).collect())
+            .unwrap_or_default();
+        ids.extend(value.get("taskid").and_then(Value::as_i64));
+        if ids.is_empty() {
+            return self.tasks.is_empty();
+        }
+        self.tasks.iter().any(|t| ids.contains(&(t.task_id as i64)))
+    }
+    /// 按回调消息更新运行，返回是否需要保存或结束运行
+    fn apply(&mut self, type_: AsstMsg, value: &Value, now: i64) -> Change {
+        let mut finished = None;
+        match type_ {
+            AsstMsg::TaskChainStart => {
+                if let Some(task) = self.task_mut(value) {
+                    task.status = Status::Running;
+                    task.started = Some(now);
+                }
+            }
+            AsstMsg::TaskChainCompleted => {
+                if let Some(task) = self.task_mut(value) {
+                    task.status = Status::Completed;
+                    task.finished = Some(now);
+                }
+            }
+            AsstMsg::TaskChainError => {
+                if let Some(task) = self.task_mut(value) {
+                    task.status = Status::Errored;
+                    task.finished = Some(now);
+                    task.errors += 1;
+                    // 保留更具体的子任务出错信息
+                    if task.error.is_none() {
+                        task.error = Some(error_message(value));
+                    }
+                }
+                self.errors += 1;
+                // 出错的是最后一个任务时，之后只会再收到AllTasksCompleted
+                if self.tasks.iter().all(|t| t.finished.is_some()) {
+                    finished = Some(Status::Errored);
+                }
+            }
+            AsstMsg::SubTaskError => {
+                if let Some(task) = self.task_mut(value) {
+                    task.errors += 1;
+                    task.error = Some(error_message(value));
+                }
+                self.errors += 1;
+            }
+            AsstMsg::TaskChainStopped => {
+                // 停止后立即开始的新运行不应被之前的停止消息结束
+                if self.task_mut(value).is_none() {
+                    return Change::None;
+                }
+                finished = Some(Status::Stopped);
+            }
+            AsstMsg::AllTasksCompleted => {
+                // 回调由写入线程异步处理，上一次运行迟到的消息不应结束新的运行
+                if !self.owns(value) {
+                    return Change::None;
+                }
+                finished = if self.tasks.iter().any(|t| t.status == Status::Errored) {
+                    Some(Status::Errored)
+                } else {
+                    Some(Status::Completed)
+                };
+            }
+            _ => return Change::None,
+        }
+        match finished {
+            Some(status) => Change::Finished(status),
+            None => Change::Updated,
+        }
+    }
+    /// 尚未完成的任务，用于重新运行
+    pub fn unfinished(&self) -> Vec<TaskSpec> {
+        self.tasks
+            .iter()
+            .filter(|t| t.status != Status::Completed)
+            .map(|t| TaskSpec {
+                type_: t.type_.clone(),
+                params: t.params.clone(),
+            })
+            .collect()
+    }
+    fn finish(&mut self, status: Status, reason: Option<String>) {
+        let now = chrono::Local::now().timestamp_millis();
+        for task in self.tasks.iter_mut() {
+            if task.status == Status::Running || (task.status == Status::Pending && status == Status::Stopped) {
+                task.status = Status::Stopped;
+                task.finished = Some(now);
+            }
+        }
+        self.end = Some(now);
+        self.duration = Some(now - self.start);
+        self.status = status;
+        self.reason = reason;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub instance: Option<i64>,
+    pub uuid: Option<String>,
+}
+
+fn save(run: &Run) -> Result<(), Error> {
+    db()?.insert(run.id.to_be_bytes(), serde_json::to_vec(run)?)?;
+    let mut latest_runs = LATEST_RUNS.lock().unwrap_or_else(PoisonError::into_inner);
+    // 被新运行取代的运行结束时不应覆盖新运行
+    if latest_runs.get(&run.instance).is_none_or(|latest| latest.id <= run.id) {
+        latest_runs.insert(run.instance, run.clone());
+    }
+    Ok(())
+}
+
+/// 出错消息的描述
+fn error_message(value: &Value) -> String {
+    if let Some(why) = value.get("why").and_then(Value::as_str) {
+        return why.to_string();
+    }
+    let task = value.get("details").and_then(|x| x.get("task")).and_then(Value::as_str);
+    match (value.get("subtask").and_then(Value::as_str), task) {
+        (Some(subtask), Some(task)) => format!("{} {} failed", subtask, task),
+        (Some(subtask), None) => format!("{} failed", subtask),
+        _ => format!(
+            "{} failed",
+            value.get("taskchain").and_then(Value::as_str).unwrap_or("task chain")
+        ),
+    }
+}
+
+/// 开始一次新的运行，tasks为(task_id, 类型, 参数)
+pub fn open(instance: i64, uuid: Option<String>, tasks: Vec<(i32, String, Value)>) -> Result<u64, Error> {
+    let mut open_runs = OPEN_RUNS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(mut run) = open_runs.remove(&instance) {
+        run.finish(Status::Stopped, Some("superseded by a new run".to_string()));
+        save(&run)?;
+    }
+    let run = Run {
+        id: db()?.generate_id()?,
+        instance,
+        uuid,
+        start: chrono::Local::now().timestamp_millis(),
+        end: None,
+        duration: None,
+        status: Status::Running,
+        tasks: tasks
+            .into_iter()
+            .map(|(task_id, type_, params)| RunTask {
+                task_id,
+                type_,
+                params,
+                status: Status::Pending,
+                started: None,
+                finished: None,
+                errors: 0,
+                error: None,
+            })
+            .collect(),
+        errors: 0,
+        reason: None,
+    };
+    save(&run)?;
+    let id = run.id;
+    open_runs.insert(instance, run);
+    Ok(id)
+}
+
+/// 结束实例正在进行的运行，没有时什么也不做
+pub fn close(instance: i64, status: Status, reason: Option<String>) -> Result<Option<u64>, Error> {
+    let mut open_runs = OPEN_RUNS.lock().unwrap_or_else(PoisonError::into_inner);
+    match open_runs.remove(&instance) {
+        Some(mut run) => {
+            run.finish(status, reason);
+            save(&run)?;
+            Ok(Some(run.id))
+        }
+        None => Ok(None),
+    }
+}
+
+/// 实例最近的运行，之后没有开始新的运行时包括已结束的运行
+pub fn latest(instance: i64) -> Option<Run> {
+    let latest_runs = LATEST_RUNS.lock().unwrap_or_else(PoisonError::into_inner);
+    latest_runs.get(&instance).cloned()
+}
+
+/// 实例正在进行的运行
+pub fn current(instance: i64) -> Option<Run> {
+    let open_runs = OPEN_RUNS.lock().unwrap_or_else(PoisonError::into_inner);
+    open_runs.get(&instance).cloned()
+}
+
+pub fn on_callback(instance: i64, type_: AsstMsg, value: &Value) -> Result<(), Error> {
+    let mut open_runs = OPEN_RUNS.lock().unwrap_or_else(PoisonError::into_inner);
+    let run = match open_runs.get_mut(&instance) {
+        Some(run) => run,
+        None => return Ok(()),
+    };
+    match run.apply(type_, value, chrono::Local::now().timestamp_millis()) {
+        Change::None => {}
+        Change::Updated => save(run)?,
+        Change::Finished(status) => {
+            if let Some(mut run) = open_runs.remove(&instance) {
+                run.finish(status, None);
+                save(&run)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn get(id: u64) -> Result<Option<Run>, Error> {
+    match db()?.get(id.to_be_bytes())? {
+        Some(v) => Ok(Some(serde_json::from_slice(&v)?)),
+        None => Ok(None),
+    }
+}
+
+/// 按时间倒序返回最多nums条符合条件的运行记录
+pub fn list(filter: &Filter, nums: usize) -> Result<Vec<Run>, Error> {
+    let mut result = Vec::new();
+    for item in db()?.iter().rev() {
+        if result.len() >= nums {
+            break;
+        }
+        let (_, v) = item?;
+        let run: Run = serde_json::from_slice(&v)?;
+        if filter.instance.is_none_or(|instance| instance == run.instance)
+            && filter.uuid.as_ref().is_none_or(|uuid| run.uuid.as_ref() == Some(uuid))
+        {
+            result.push(run);
+        }
+    }
+    Ok(result)
+}
+
+pub fn flush() -> Result<(), Error> {
+    db()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(task_ids: &[i32]) -> Run {
+        Run {
+            id: 1,
+            instance: 1,
+            uuid: None,
+            start: 0,
+            end: None,
+            duration: None,
+            status: Status::Running,
+            tasks: task_ids
+                .iter()
+                .map(|id| RunTask {
+                    task_id: *id,
+                    type_: "Fight".to_string(),
+                    params: json!({}),
+                    status: Status::Pending,
+                    started: None,
+                    finished: None,
+                    errors: 0,
+                    error: None,
+                })
+                .collect(),
+            errors: 0,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn tasks_complete_then_run_completes() {
+        let mut run = run(&[1, 2]);
+        assert_eq!(run.apply(AsstMsg::TaskChainStart, &json!({"taskid": 1}), 10), Change::Updated);
+        assert_eq!((run.tasks[0].status, run.tasks[0].started), (Status::Running, Some(10)));
+        run.apply(AsstMsg::TaskChainCompleted, &json!({"taskid": 1}), 20);
+        run.apply(AsstMsg::TaskChainCompleted, &json!({"taskid": 2}), 30);
+        assert_eq!(run.tasks[1].finished, Some(30));
+        let all = json!({"taskid": 2, "finished_tasks": [1, 2]});
+        assert_eq!(run.apply(AsstMsg::AllTasksCompleted, &all, 40), Change::Finished(Status::Completed));
+    }
+
+    #[test]
+    fn errors_keep_the_subtask_message() {
+        let mut run = run(&[1, 2]);
+        run.apply(AsstMsg::SubTaskError, &json!({"taskid": 1, "subtask": "ProcessTask", "details": {"task": "StartButton"}}), 10);
+        assert_eq!(run.apply(AsstMsg::TaskChainError, &json!({"taskid": 1, "taskchain": "Fight"}), 20), Change::Updated);
+        assert_eq!(run.tasks[0].status, Status::Errored);
+        assert_eq!(run.tasks[0].error.as_deref(), Some("ProcessTask StartButton failed"));
+        assert_eq!((run.tasks[0].errors, run.errors), (2, 2));
+        let all = json!({"finished_tasks": [1, 2]});
+        assert_eq!(run.apply(AsstMsg::AllTasksCompleted, &all, 30), Change::Finished(Status::Errored));
+    }
+
+    #[test]
+    fn error_in_last_task_finishes_run() {
+        let mut run = run(&[1]);
+        let change = run.apply(AsstMsg::TaskChainError, &json!({"taskid": 1, "taskchain": "Fight"}), 10);
+        assert_eq!(change, Change::Finished(Status::Errored));
+        assert_eq!(run.tasks[0].error.as_deref(), Some("Fight failed"));
+    }
+
+    #[test]
+    fn messages_from_previous_runs_are_ignored() {
+        let mut run = run(&[3, 4]);
+        assert_eq!(run.apply(AsstMsg::AllTasksCompleted, &json!({"taskid": 2, "finished_tasks": [1, 2]}), 10), Change::None);
+        assert_eq!(run.apply(AsstMsg::AllTasksCompleted, &json!({}), 10), Change::None);
+        assert_eq!(run.apply(AsstMsg::TaskChainStopped, &json!({"taskid": 2}), 10), Change::None);
+        assert_eq!(run.apply(AsstMsg::TaskChainStopped, &json!({"taskid": 3}), 10), Change::Finished(Status::Stopped));
+    }
+
+    #[test]
+    fn stopping_marks_unfinished_tasks() {
+        let mut run = run(&[1, 2, 3]);
+        run.apply(AsstMsg::TaskChainCompleted, &json!({"taskid": 1}), 10);
+        run.apply(AsstMsg::TaskChainStart, &json!({"taskid": 2}), 20);
+        run.finish(Status::Stopped, Some("user".to_string()));
+        let statuses: Vec<Status> = run.tasks.iter().map(|t| t.status).collect();
+        assert_eq!(statuses, [Status::Completed, Status::Stopped, Status::Stopped]);
+        assert_eq!(run.status, Status::Stopped);
+        assert_eq!(run.unfinished().len(), 2);
+    }
+}
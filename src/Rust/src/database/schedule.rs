@@ -0,0 +1,207 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use crate::config::Schedule;
+use crate::config;
+use super::Error;
+lazy_static! {
+    /// 每个计划一棵树保存触发记录，通过接口添加的计划保存在SCHEDULES中
+    static ref SCHEDULE_DB: Result<sled::Db, sled::Error> = {
+        let mut p = PathBuf::new();
+        p.push(config::current().database.path.clone());
+        p.push("schedule");
+        sled::open(p.as_os_str())
+    };
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    SCHEDULE_DB.as_ref().map_err(|e| Error::Sled(e.clone()))
+}
+
+const SCHEDULES_TREE: &str = "__schedules";
+
+/// 以"__"开头的名称保留给内部的树，如SCHEDULES_TREE
+pub fn reserved(name: &str) -> bool {
+    name.is_empty() || name.starts_with("__")
+}
+
+fn schedules(db: &sled::Db) -> Result<sled::Tree, Error> {
+    Ok(db.open_tree(SCHEDULES_TREE)?)
+}
+
+/// 计划的执行记录所在的树，不存在时不创建
+fn existing_tree(db: &sled::Db, name: &str) -> Result<Option<sled::Tree>, Error> {
+    if reserved(name) {
+        return Ok(None);
+    }
+    super::existing_tree(db, name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Started,
+    Skipped,
+    Queued,
+    Failed,
+}
+
+/// 计划的一次触发
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Firing {
+    pub id: u64,
+    /// 按cron表达式应触发的时间
+    pub scheduled: i64,
+    pub time: i64,
+    pub outcome: Outcome,
+    pub instance: Option<i64>,
+    pub run_id: Option<u64>,
+    pub reason: Option<String>,
+}
+
+pub fn save(schedule: &Schedule) -> Result<(), Error> {
+    save_in(db()?, schedule)
+}
+
+fn save_in(db: &sled::Db, schedule: &Schedule) -> Result<(), Error> {
+    schedules(db)?.insert(schedule.name.as_bytes(), serde_json::to_vec(schedule)?)?;
+    Ok(())
+}
+
+pub fn all() -> Result<Vec<Schedule>, Error> {
+    all_in(db()?)
+}
+
+fn all_in(db: &sled::Db) -> Result<Vec<Schedule>, Error> {
+    let mut result = Vec::new();
+    for item in schedules(db)?.iter() {
+        let (_, v) = item?;
+        result.push(serde_json::from_slice(&v)?);
+    }
+    Ok(result)
+}
+
+/// 删除通过接口添加的计划及其触发记录，计划不存在时什么也不做
+pub fn remove(name: &str) -> Result<bool, Error> {
+    remove_in(db()?, name)
+}
+
+fn remove_in(db: &sled::Db, name: &str) -> Result<bool, Error> {
+    if reserved(name) {
+        return Ok(false);
+    }
+    let removed = schedules(db)?.remove(name.as_bytes())?.is_some();
+    if removed {
+        db.drop_tree(name)?;
+    }
+    Ok(removed)
+}
+
+pub fn record(
+    name: &str,
+    scheduled: i64,
+    outcome: Outcome,
+    instance: Option<i64>,
+    run_id: Option<u64>,
+    reason: Option<String>,
+) -> Result<Firing, Error> {
+    record_in(db()?, name, scheduled, outcome, instance, run_id, reason)
+}
+
+fn record_in(
+    db: &sled::Db,
+    name: &str,
+    scheduled: i64,
+    outcome: Outcome,
+    instance: Option<i64>,
+    run_id: Option<u64>,
+    reason: Option<String>,
+) -> Result<Firing, Error> {
+    if reserved(name) {
+        return Err(Error::ReservedName);
+    }
+    let firing = Firing {
+        id: db.generate_id()?,
+        scheduled,
+        time: chrono::Local::now().timestamp_millis(),
+        outcome,
+        instance,
+        run_id,
+        reason,
+    };
+    db.open_tree(name)?
+        .insert(firing.id.to_be_bytes(), serde_json::to_vec(&firing)?)?;
+    Ok(firing)
+}
+
+/// 按时间倒序返回最多nums条触发记录
+pub fn history(name: &str, nums: usize) -> Result<Vec<Firing>, Error> {
+    history_in(db()?, name, nums)
+}
+
+fn history_in(db: &sled::Db, name: &str, nums: usize) -> Result<Vec<Firing>, Error> {
+    let tree = match existing_tree(db, name)? {
+        Some(tree) => tree,
+        None => return Ok(Vec::new()),
+    };
+    let mut result = Vec::new();
+    for item in tree.iter().rev().take(nums) {
+        let (_, v) = item?;
+        result.push(serde_json::from_slice(&v)?);
+    }
+    Ok(result)
+}
+
+pub fn flush() -> Result<(), Error> {
+    db()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schedule(name: &str) -> Schedule {
+        serde_json::from_value(json!({"name": name, "cron": "0 4 * * *", "instance": 1, "tasks": []})).unwrap()
+    }
+
+    fn temporary_db() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    #[test]
+    fn remove_ignores_reserved_and_unknown_names() {
+        let db = temporary_db();
+        save_in(&db, &schedule("daily")).unwrap();
+        record_in(&db, "daily", 1, Outcome::Started, Some(1), Some(1), None).unwrap();
+        record_in(&db, "config", 1, Outcome::Skipped, Some(1), None, None).unwrap();
+        assert!(!remove_in(&db, SCHEDULES_TREE).unwrap());
+        assert!(!remove_in(&db, "unknown").unwrap());
+        // 配置文件中的计划不在数据库中，触发记录应保留
+        assert!(!remove_in(&db, "config").unwrap());
+        assert_eq!(all_in(&db).unwrap().len(), 1);
+        assert_eq!(history_in(&db, "daily", 10).unwrap().len(), 1);
+        assert_eq!(history_in(&db, "config", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn remove_drops_history() {
+        let db = temporary_db();
+        save_in(&db, &schedule("daily")).unwrap();
+        record_in(&db, "daily", 1, Outcome::Started, Some(1), Some(1), None).unwrap();
+        assert!(remove_in(&db, "daily").unwrap());
+        assert!(all_in(&db).unwrap().is_empty());
+        assert!(history_in(&db, "daily", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn history_of_reserved_or_unknown_names_is_empty() {
+        let db = temporary_db();
+        save_in(&db, &schedule("daily")).unwrap();
+        assert!(history_in(&db, SCHEDULES_TREE, 10).unwrap().is_empty());
+        assert!(history_in(&db, "unknown", 10).unwrap().is_empty());
+        assert!(!db.tree_names().iter().any(|x| x == "unknown".as_bytes()));
+        assert!(record_in(&db, SCHEDULES_TREE, 1, Outcome::Failed, None, None, None).is_err());
+    }
+}
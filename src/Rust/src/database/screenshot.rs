@@ -0,0 +1,130 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use crate::config;
+use super::{existing_tree, Error};
+lazy_static! {
+    /// 每个uuid一棵树，键为对应消息的id，值为png图片
+    static ref SCREENSHOT_DB: Result<sled::Db, sled::Error> = {
+        let mut p = PathBuf::new();
+        p.push(config::current().database.path.clone());
+        p.push("screenshot");
+        sled::open(p.as_os_str())
+    };
+}
+
+pub(super) fn db() -> Result<&'static sled::Db, Error> {
+    SCREENSHOT_DB.as_ref().map_err(|e| Error::Sled(e.clone()))
+}
+
+const INDEX_TREE: &str = "__index";
+
+/// 按消息id排序的所有截图，用于按时间淘汰
+fn index() -> Result<sled::Tree, Error> {
+    Ok(db()?.open_tree(INDEX_TREE)?)
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    uuid: String,
+    size: u64,
+}
+
+pub fn insert(uuid: &str, msg_id: u64, image: &[u8]) -> Result<(), Error> {
+    let entry = IndexEntry {
+        uuid: uuid.to_string(),
+        size: image.len() as u64,
+    };
+    db()?.open_tree(uuid)?.insert(msg_id.to_be_bytes(), image)?;
+    index()?.insert(msg_id.to_be_bytes(), serde_json::to_vec(&entry)?)?;
+    evict(config::current().screenshot.max_total_size)
+}
+
+pub fn get(uuid: &str, msg_id: u64) -> Result<Option<Vec<u8>>, Error> {
+    get_in(db()?, uuid, msg_id)
+}
+
+pub fn contains(uuid: &str, msg_id: u64) -> Result<bool, Error> {
+    contains_in(db()?, uuid, msg_id)
+}
+
+fn get_in(db: &sled::Db, uuid: &str, msg_id: u64) -> Result<Option<Vec<u8>>, Error> {
+    let image = match tree(db, uuid)? {
+        Some(tree) => tree.get(msg_id.to_be_bytes())?,
+        None => None,
+    };
+    Ok(image.map(|x| x.to_vec()))
+}
+
+fn contains_in(db: &sled::Db, uuid: &str, msg_id: u64) -> Result<bool, Error> {
+    match tree(db, uuid)? {
+        Some(tree) => Ok(tree.contains_key(msg_id.to_be_bytes())?),
+        None => Ok(false),
+    }
+}
+
+/// 实例的截图所在的树，不存在时不创建
+fn tree(db: &sled::Db, uuid: &str) -> Result<Option<sled::Tree>, Error> {
+    if uuid == INDEX_TREE {
+        return Ok(None);
+    }
+    existing_tree(db, uuid)
+}
+
+/// 从最早的截图开始删除，直到总大小不超过max_total_size
+fn evict(max_total_size: u64) -> Result<(), Error> {
+    let mut entries = Vec::new();
+    let mut total = 0;
+    let index = index()?;
+    for item in index.iter() {
+        let (k, v) = item?;
+        let entry: IndexEntry = serde_json::from_slice(&v)?;
+        total += entry.size;
+        entries.push((k, entry));
+    }
+    for (k, entry) in entries {
+        if total <= max_total_size {
+            break;
+        }
+        db()?.open_tree(&entry.uuid)?.remove(&k)?;
+        index.remove(&k)?;
+        total -= entry.size;
+    }
+    Ok(())
+}
+
+pub fn drop(uuid: &str) -> Result<(), Error> {
+    db()?.drop_tree(uuid)?;
+    let index = index()?;
+    for item in index.iter() {
+        let (k, v) = item?;
+        let entry: IndexEntry = serde_json::from_slice(&v)?;
+        if entry.uuid == uuid {
+            index.remove(k)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn flush() -> Result<(), Error> {
+    db()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_do_not_create_trees() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let trees = db.tree_names().len();
+        assert!(get_in(&db, "uuid", 1).unwrap().is_none());
+        assert!(!contains_in(&db, "uuid", 1).unwrap());
+        assert!(!contains_in(&db, INDEX_TREE, 1).unwrap());
+        assert_eq!(db.tree_names().len(), trees);
+        db.open_tree("uuid").unwrap().insert(1u64.to_be_bytes(), &[1, 2][..]).unwrap();
+        assert_eq!(get_in(&db, "uuid", 1).unwrap(), Some(vec![1, 2]));
+        assert!(contains_in(&db, "uuid", 1).unwrap());
+    }
+}
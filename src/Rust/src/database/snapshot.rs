@@ -0,0 +1,117 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use crate::config;
+use super::{existing_tree, Error};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot<T> {
+    pub id: u64,
+    pub time: i64,
+    pub instance: i64,
+    pub data: T,
+}
+
+/// 按uuid分树保存的快照，树内的键为自增id
+pub struct Snapshots<T> {
+    /// 打开失败时保留错误，使用时返回
+    db: Result<sled::Db, sled::Error>,
+    _data: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Snapshots<T> {
+    pub fn open(name: &str) -> Self {
+        let mut p = PathBuf::new();
+        p.push(config::current().database.path.clone());
+        p.push(name);
+        Snapshots {
+            db: sled::open(p.as_os_str()),
+            _data: PhantomData,
+        }
+    }
+    pub fn db(&self) -> Result<&sled::Db, Error> {
+        self.db.as_ref().map_err(|e| Error::Sled(e.clone()))
+    }
+    pub fn flush(&self) -> Result<(), Error> {
+        self.db()?.flush()?;
+        Ok(())
+    }
+    pub fn insert(&self, uuid: &str, instance: i64, data: T) -> Result<u64, Error> {
+        let tree = self.db()?.open_tree(uuid)?;
+        let id = self.db()?.generate_id()?;
+        let snapshot = Snapshot {
+            id,
+            time: chrono::Local::now().timestamp_millis(),
+            instance,
+            data,
+        };
+        tree.insert(id.to_be_bytes(), serde_json::to_vec(&snapshot)?)?;
+        Ok(id)
+    }
+    pub fn get(&self, uuid: &str, id: u64) -> Result<Option<Snapshot<T>>, Error> {
+        let tree = match existing_tree(self.db()?, uuid)? {
+            Some(tree) => tree,
+            None => return Ok(None),
+        };
+        match tree.get(id.to_be_bytes())? {
+            Some(v) => Ok(Some(serde_json::from_slice(&v)?)),
+            None => Ok(None),
+        }
+    }
+    pub fn latest(&self, uuid: &str) -> Result<Option<Snapshot<T>>, Error> {
+        let tree = match existing_tree(self.db()?, uuid)? {
+            Some(tree) => tree,
+            None => return Ok(None),
+        };
+        match tree.last()? {
+            Some((_, v)) => Ok(Some(serde_json::from_slice(&v)?)),
+            None => Ok(None),
+        }
+    }
+    /// 按时间倒序返回最多nums个快照
+    pub fn list(&self, uuid: &str, nums: usize) -> Result<Vec<Snapshot<T>>, Error> {
+        let mut result = Vec::new();
+        let tree = match existing_tree(self.db()?, uuid)? {
+            Some(tree) => tree,
+            None => return Ok(result),
+        };
+        for item in tree.iter().rev().take(nums) {
+            let (_, v) = item?;
+            result.push(serde_json::from_slice(&v)?);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary() -> Snapshots<i32> {
+        Snapshots {
+            db: sled::Config::new().temporary(true).open(),
+            _data: PhantomData,
+        }
+    }
+
+    #[test]
+    fn reads_do_not_create_trees() {
+        let snapshots = temporary();
+        let trees = snapshots.db().unwrap().tree_names().len();
+        assert!(snapshots.get("uuid", 1).unwrap().is_none());
+        assert!(snapshots.latest("uuid").unwrap().is_none());
+        assert!(snapshots.list("uuid", 10).unwrap().is_empty());
+        assert_eq!(snapshots.db().unwrap().tree_names().len(), trees);
+    }
+
+    #[test]
+    fn list_returns_newest_first() {
+        let snapshots = temporary();
+        let first = snapshots.insert("uuid", 1, 10).unwrap();
+        let second = snapshots.insert("uuid", 1, 20).unwrap();
+        let list = snapshots.list("uuid", 10).unwrap();
+        assert_eq!(list.iter().map(|x| x.id).collect::<Vec<_>>(), vec![second, first]);
+        assert_eq!(snapshots.latest("uuid").unwrap().unwrap().data, 20);
+        assert_eq!(snapshots.get("uuid", first).unwrap().unwrap().data, 10);
+    }
+}
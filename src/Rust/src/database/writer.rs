@@ -0,0 +1,165 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::time::{Duration, Instant};
+use crate::{callback, config};
+use super::msg::{self, Msg};
+
+/// 队列已满时重试放入flush命令的间隔
+const FLUSH_RETRY: Duration = Duration::from_millis(10);
+
+/// 回调线程收到的原始消息
+pub struct Callback {
+    pub instance: i64,
+    pub type_: u32,
+    pub time: i64,
+    pub body: String,
+}
+
+enum Command {
+    Callback(Callback),
+    Flush(mpsc::Sender<()>),
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub queued: usize,
+    pub capacity: usize,
+    pub dropped: u64,
+    pub written: u64,
+}
+
+lazy_static! {
+    static ref SENDER: SyncSender<Command> = {
+        let (sender, receiver) = mpsc::sync_channel(config::current().database.queue_capacity);
+        std::thread::Builder::new()
+            .name("msg-writer".to_string())
+            .spawn(move || run(receiver))
+            .unwrap();
+        sender
+    };
+}
+
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+static WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+/// 放入写入队列，队列已满时丢弃该消息
+pub fn push(callback: Callback) {
+    QUEUED.fetch_add(1, Ordering::Relaxed);
+    if let Err(e) = SENDER.try_send(Command::Callback(callback)) {
+        QUEUED.fetch_sub(1, Ordering::Relaxed);
+        let dropped = DROPPED.fetch_add(1, Ordering::Relaxed) + 1;
+        // 持续积压时每100条提示一次
+        if matches!(e, TrySendError::Full(_)) && dropped % 100 == 1 {
+            tracing::warn!("message queue is full, {} messages dropped in total", dropped);
+        }
+    }
+}
+
+/// 等待队列中已有的消息全部写入并落盘，队列已满时等待放入的时间也计入timeout
+pub fn flush(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let (ack, done) = mpsc::channel();
+    let mut command = Command::Flush(ack);
+    loop {
+        match SENDER.try_send(command) {
+            Ok(()) => break,
+            Err(TrySendError::Full(c)) if Instant::now() < deadline => {
+                command = c;
+                std::thread::sleep(FLUSH_RETRY);
+            }
+            Err(_) => return false,
+        }
+    }
+    done.recv_timeout(deadline.saturating_duration_since(Instant::now())).is_ok()
+}
+
+pub fn stats() -> Stats {
+    Stats {
+        queued: QUEUED.load(Ordering::Relaxed),
+        capacity: config::current().database.queue_capacity,
+        dropped: DROPPED.load(Ordering::Relaxed),
+        written: WRITTEN.load(Ordering::Relaxed),
+    }
+}
+
+fn run(receiver: Receiver<Command>) {
+    while let Ok(command) = receiver.recv() {
+        // 每批重新读取，重新加载配置后立即生效
+        let batch_size = config::current().database.batch_size.max(1);
+        let mut commands = vec![command];
+        while commands.len() < batch_size {
+            match receiver.try_recv() {
+                Ok(command) => commands.push(command),
+                Err(_) => break,
+            }
+        }
+        let mut callbacks = Vec::with_capacity(commands.len());
+        for command in commands {
+            match command {
+                Command::Callback(callback) => {
+                    QUEUED.fetch_sub(1, Ordering::Relaxed);
+                    callbacks.push(callback);
+                }
+                Command::Flush(ack) => {
+                    write(std::mem::take(&mut callbacks));
+                    if let Err(e) = msg::flush() {
+                        tracing::warn!("failed to flush message database: {:?}", e);
+                    }
+                    let _ = ack.send(());
+                }
+            }
+        }
+        write(callbacks);
+    }
+}
+
+fn write(callbacks: Vec<Callback>) {
+    if callbacks.is_empty() {
+        return;
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut msgs = Vec::with_capacity(callbacks.len());
+        for callback in callbacks {
+            let value = match serde_json::from_str::<Value>(&callback.body) {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            // 没有uuid的消息不入库，但仍然分发
+            let uuid = value.get("uuid").and_then(Value::as_str).map(str::to_string);
+            let persist = uuid.is_some();
+            let msg = Msg {
+                time: callback.time,
+                type_: callback.type_,
+                uuid: uuid.unwrap_or_default(),
+                body: callback.body,
+                instance: Some(callback.instance),
+                task_id: value.get("taskid").and_then(Value::as_i64).map(|x| x as i32),
+            };
+            msgs.push((callback.instance, msg, value, persist));
+        }
+        let persisted = msgs.iter().filter(|(_, _, _, persist)| *persist);
+        // 写入失败时仍然分发，运行记录、自动重连等状态不依赖消息是否入库
+        let mut ids = match msg::insert_batch(persisted.clone().map(|(_, msg, _, _)| msg)) {
+            Ok(ids) => {
+                WRITTEN.fetch_add(ids.len() as u64, Ordering::Relaxed);
+                ids.into_iter()
+            }
+            Err(e) => {
+                tracing::error!("failed to write {} messages: {:?}", persisted.count(), e);
+                Vec::new().into_iter()
+            }
+        };
+        for (instance, msg, value, persist) in &msgs {
+            let id = if *persist { ids.next() } else { None };
+            callback::dispatch(*instance, id, msg, value);
+        }
+    }));
+    if result.is_err() {
+        tracing::error!("message writer panicked while writing a batch");
+    }
+}
@@ -0,0 +1,291 @@
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use crate::{
+    api::{Error, MaaManager, MAA_MANAGER},
+    database::{self, flow::{Flow, FlowStatus, Step, StepSpec, StepStatus}},
+    maa_sys::AsstMsg,
+    metrics,
+    shutdown,
+};
+
+const TICK: Duration = Duration::from_millis(500);
+
+/// 正在进行的流程
+struct Active {
+    flow: Flow,
+    /// 当前任务的结果，收到TaskChainCompleted为true，TaskChainError为false
+    outcome: Option<bool>,
+}
+
+lazy_static! {
+    /// 每个实例同时只有一个流程
+    static ref ACTIVE: Mutex<HashMap<i64, Active>> = Mutex::new(HashMap::new());
+}
+
+pub fn start() {
+    std::thread::Builder::new()
+        .name("flow".to_string())
+        .spawn(|| loop {
+            tick();
+            std::thread::sleep(TICK);
+        })
+        .unwrap();
+}
+
+fn save(flow: &Flow) {
+    if let Err(e) = database::flow::save(flow) {
+        tracing::warn!("failed to save flow {}: {:?}", flow.id, e);
+    }
+}
+
+fn validate(steps: &[StepSpec]) -> Result<(), Error> {
+    let mut names = HashSet::new();
+    for step in steps {
+        // run_if只能引用之前的步骤
+        if let Some(name) = &step.run_if {
+            if !names.contains(name.as_str()) {
+                return Err(Error::InvalidRequest);
+            }
+        }
+        if step.name.is_empty() || !names.insert(step.name.as_str()) {
+            return Err(Error::InvalidRequest);
+        }
+        if !matches!(step.params, Value::Null | Value::Object(_)) {
+            return Err(Error::InvalidRequest);
+        }
+    }
+    if steps.is_empty() {
+        return Err(Error::InvalidRequest);
+    }
+    Ok(())
+}
+
+/// 开始流程并运行第一步，返回流程的id
+pub fn create(manager: &mut MaaManager, instance: i64, steps: Vec<StepSpec>) -> Result<u64, Error> {
+    validate(&steps)?;
+    let maa = manager.get(instance).ok_or(Error::InstanceNotFound)?;
+    let mut active_flows = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    if maa.running() || active_flows.contains_key(&instance) {
+        return Err(Error::InstanceBusy);
+    }
+    let flow = Flow {
+        id: database::flow::generate_id().map_err(|_| Error::Internal)?,
+        instance,
+        status: FlowStatus::Running,
+        steps: steps
+            .into_iter()
+            .map(|spec| Step {
+                spec,
+                status: StepStatus::Pending,
+                attempts: 0,
+                task_id: None,
+                run_ids: Vec::new(),
+                started: None,
+                finished: None,
+                error: None,
+            })
+            .collect(),
+        current: None,
+        start: chrono::Local::now().timestamp_millis(),
+        end: None,
+        reason: None,
+    };
+    let id = flow.id;
+    let mut active = Active { flow, outcome: None };
+    advance(manager, &mut active);
+    save(&active.flow);
+    if active.flow.status == FlowStatus::Running {
+        active_flows.insert(instance, active);
+    }
+    Ok(id)
+}
+
+/// 停止流程和实例正在进行的运行
+pub fn stop(manager: &mut MaaManager, id: u64, reason: &str) -> Result<(), Error> {
+    let mut active_flows = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    let instance = active_flows
+        .values()
+        .find(|x| x.flow.id == id)
+        .map(|x| x.flow.instance)
+        .ok_or(Error::FlowNotFound)?;
+    let mut active = active_flows.remove(&instance).unwrap();
+    finish(&mut active.flow, FlowStatus::Stopped, Some(reason.to_string()));
+    save(&active.flow);
+    manager.stop(instance, reason)
+}
+
+/// 关闭服务时停止所有流程，实例由调用者停止
+pub fn shutdown() {
+    let mut active_flows = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    for (_, mut active) in active_flows.drain() {
+        finish(&mut active.flow, FlowStatus::Stopped, Some("server shutting down".to_string()));
+        save(&active.flow);
+    }
+}
+
+/// 在写入线程中调用，不能访问MAA_MANAGER
+pub fn on_callback(instance: i64, type_: AsstMsg, value: &Value) {
+    let mut active_flows = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    let active = match active_flows.get_mut(&instance) {
+        Some(active) => active,
+        None => return,
+    };
+    let task_id = value.get("taskid").and_then(Value::as_i64);
+    let current = active.flow.current.and_then(|i| active.flow.steps[i].task_id);
+    if task_id.is_none() || task_id != current.map(|x| x as i64) {
+        return;
+    }
+    match type_ {
+        AsstMsg::TaskChainCompleted => active.outcome = Some(true),
+        AsstMsg::TaskChainError => active.outcome = Some(false),
+        AsstMsg::TaskChainStopped => {
+            let mut active = active_flows.remove(&instance).unwrap();
+            finish(&mut active.flow, FlowStatus::Stopped, Some("task stopped".to_string()));
+            save(&active.flow);
+        }
+        _ => {}
+    }
+}
+
+fn tick() {
+    if shutdown::started() {
+        return;
+    }
+    let mut manager = match MAA_MANAGER.lock() {
+        Ok(manager) => manager,
+        Err(_) => return,
+    };
+    let mut active_flows = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut finished = Vec::new();
+    for (instance, active) in active_flows.iter_mut() {
+        match manager.get(*instance) {
+            None => {
+                finish(&mut active.flow, FlowStatus::Stopped, Some("instance deleted".to_string()));
+            }
+            // 等maa运行结束后再开始下一个任务
+            Some(maa) if active.outcome.is_some() && !maa.running() => advance(&mut manager, active),
+            _ => continue,
+        }
+        save(&active.flow);
+        if active.flow.status != FlowStatus::Running {
+            finished.push(*instance);
+        }
+    }
+    for instance in finished {
+        active_flows.remove(&instance);
+    }
+}
+
+fn finish(flow: &mut Flow, status: FlowStatus, reason: Option<String>) {
+    let now = chrono::Local::now().timestamp_millis();
+    for step in flow.steps.iter_mut() {
+        if matches!(step.status, StepStatus::Running | StepStatus::Fallback) {
+            step.status = StepStatus::Stopped;
+            step.finished = Some(now);
+        }
+    }
+    flow.status = status;
+    flow.end = Some(now);
+    flow.reason = reason;
+}
+
+/// 添加一个任务并开始运行，返回任务id和运行记录id
+///
+/// 运行中只能有这一个任务：实例中还有未运行的任务时拒绝启动，任务队列保持不动
+fn launch(manager: &mut MaaManager, instance: i64, type_: &str, params: &Value) -> Result<(i32, u64), Error> {
+    let params = match params {
+        Value::Null => "{}".to_string(),
+        params => params.to_string(),
+    };
+    let maa = manager.get_mut(instance).ok_or(Error::InstanceNotFound)?;
+    if !maa.get_tasks()?.is_empty() {
+        return Err(Error::InstanceBusy);
+    }
+    let task_id = maa.create_task(type_, &params)?;
+    metrics::task_appended(type_);
+    let run_id = manager.start_run(instance)?;
+    Ok((task_id, run_id))
+}
+
+/// 处理当前任务的结果，并开始下一个任务，直到有任务在运行或流程结束
+fn advance(manager: &mut MaaManager, active: &mut Active) {
+    let instance = active.flow.instance;
+    let _span = tracing::info_span!("flow", instance, flow = active.flow.id).entered();
+    let now = chrono::Local::now().timestamp_millis();
+    // 当前步骤需要运行的任务
+    let mut next: Option<(String, Value)> = None;
+    if let Some(i) = active.flow.current {
+        let success = active.outcome.take().unwrap_or(false);
+        let step = &mut active.flow.steps[i];
+        match (step.status, success) {
+            (StepStatus::Running, true) => step.status = StepStatus::Completed,
+            (StepStatus::Running, false) if step.attempts < step.spec.retries => {
+                step.attempts += 1;
+                next = Some((step.spec.type_.clone(), step.spec.params.clone()));
+            }
+            (StepStatus::Running, false) if step.spec.fallback.is_some() => {
+                let fallback = step.spec.fallback.clone().unwrap();
+                step.status = StepStatus::Fallback;
+                next = Some((fallback.type_, fallback.params));
+            }
+            (StepStatus::Fallback, true) => step.status = StepStatus::FallbackCompleted,
+            _ => step.status = StepStatus::Errored,
+        }
+        if next.is_none() {
+            step.finished = Some(now);
+        }
+    }
+    loop {
+        if let Some((type_, params)) = next.take() {
+            let i = active.flow.current.unwrap();
+            match launch(manager, instance, &type_, &params) {
+                Ok((task_id, run_id)) => {
+                    let step = &mut active.flow.steps[i];
+                    step.task_id = Some(task_id);
+                    step.run_ids.push(run_id);
+                    return;
+                }
+                Err(e) => {
+                    let step = &mut active.flow.steps[i];
+                    step.status = StepStatus::Errored;
+                    step.error = Some(e.message().to_string());
+                    step.finished = Some(now);
+                }
+            }
+        }
+        // 跳过条件不满足的步骤
+        let mut i = active.flow.current.map_or(0, |i| i + 1);
+        while i < active.flow.steps.len() {
+            let skip = match &active.flow.steps[i].spec.run_if {
+                Some(name) => !active
+                    .flow
+                    .steps
+                    .iter()
+                    .any(|x| x.spec.name == *name && x.status == StepStatus::Completed),
+                None => false,
+            };
+            if !skip {
+                break;
+            }
+            active.flow.steps[i].status = StepStatus::Skipped;
+            i += 1;
+        }
+        if i >= active.flow.steps.len() {
+            let status = if active.flow.steps.iter().any(|x| x.status == StepStatus::Errored) {
+                FlowStatus::Failed
+            } else {
+                FlowStatus::Completed
+            };
+            finish(&mut active.flow, status, None);
+            return;
+        }
+        let step = &mut active.flow.steps[i];
+        step.status = StepStatus::Running;
+        step.started = Some(now);
+        next = Some((step.spec.type_.clone(), step.spec.params.clone()));
+        active.flow.current = Some(i);
+    }
+}
@@ -0,0 +1,147 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use crate::{api::MaaManager, config::Core, database, maa_sys::Maa, supervisor};
+
+/// 写入检查的结果在这段时间内复用，频繁的探测不会每次都写盘
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    /// 最近一次写入检查的时间和结果
+    static ref PROBE: Mutex<Option<(Instant, Result<(), String>)>> = Mutex::new(None);
+    /// 最近一次加载资源的结果
+    static ref RESOURCE: Mutex<Result<(), String>> = Mutex::new(Err("not loaded".to_string()));
+    /// 启动时迁移消息数据库的结果
+    static ref MIGRATION: Mutex<Result<(), String>> = Mutex::new(Ok(()));
+    /// 启动时设置MaaCore的结果
+    static ref CORE: Mutex<Result<(), String>> = Mutex::new(Ok(()));
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+impl<E: std::fmt::Debug> From<Result<(), E>> for Check {
+    fn from(result: Result<(), E>) -> Self {
+        match result {
+            Ok(()) => Check { ok: true, error: None },
+            Err(e) => Check { ok: false, error: Some(format!("{:?}", e)) },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstanceHealth {
+    pub id: i64,
+    pub target: Option<String>,
+    /// 已连接且没有在重连
+    pub connected: bool,
+    pub running: bool,
+    pub supervisor: Option<supervisor::Status>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub resource: Check,
+    pub database: Check,
+    pub core: Check,
+    /// 不影响ready
+    pub instances: Option<Vec<InstanceHealth>>,
+}
+
+/// 设置用户目录和静态选项，需要在加载资源前调用，结果由/readyz报告
+pub fn setup_core(core: &Core) -> Result<(), String> {
+    let mut errors = Vec::new();
+    if let Some(dir) = &core.user_dir {
+        if let Err(e) = Maa::set_working_directory(dir) {
+            errors.push(format!("failed to set user dir to {}: {:?}", dir, e));
+        }
+    }
+    for (name, value) in &core.static_options {
+        let result = match Core::static_option_key(name) {
+            Some(key) => Maa::set_static_option(key, value),
+            None => Err(crate::maa_sys::Error::Unknown),
+        };
+        if let Err(e) = result {
+            errors.push(format!("failed to set static option {} to {}: {:?}", name, value, e));
+        }
+    }
+    let result = if errors.is_empty() { Ok(()) } else { Err(errors.join("; ")) };
+    *CORE.lock().unwrap_or_else(PoisonError::into_inner) = result.clone();
+    result
+}
+
+/// 消息数据库是否可写，结果缓存PROBE_INTERVAL
+fn probe() -> Result<(), String> {
+    let mut probe = PROBE.lock().unwrap_or_else(PoisonError::into_inner);
+    match &*probe {
+        Some((time, result)) if time.elapsed() < PROBE_INTERVAL => result.clone(),
+        _ => {
+            let result = database::msg::probe().map_err(|e| e.to_string());
+            *probe = Some((Instant::now(), result.clone()));
+            result
+        }
+    }
+}
+
+/// 迁移消息数据库，结果由/readyz报告
+pub fn migrate() -> Result<(), String> {
+    let result = database::msg::migrate().map_err(|e| format!("failed to migrate message database: {}", e));
+    *MIGRATION.lock().unwrap_or_else(PoisonError::into_inner) = result.clone();
+    result
+}
+
+/// 加载资源，结果由/readyz报告
+pub fn load_resource(path: &str) -> Result<(), String> {
+    let result = Maa::load_resource(path).map_err(|e| format!("failed to load resource from {}: {:?}", path, e));
+    *RESOURCE.lock().unwrap_or_else(PoisonError::into_inner) = result.clone();
+    result
+}
+
+pub fn readiness(manager: Option<&MaaManager>) -> Readiness {
+    let resource: Check = RESOURCE.lock().unwrap_or_else(PoisonError::into_inner).clone().into();
+    let database: Check = MIGRATION
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .and_then(|()| database::check())
+        .and_then(|()| probe())
+        .into();
+    let core: Check = match CORE.lock().unwrap_or_else(PoisonError::into_inner).clone() {
+        Ok(()) => Maa::get_version().map(|_| ()).into(),
+        Err(e) => Err(e).into(),
+    };
+    let instances = manager.map(|manager| {
+        manager
+            .get_all_id()
+            .into_iter()
+            .filter_map(|id| {
+                let maa = manager.get(id)?;
+                let supervisor = supervisor::get(id).map(|x| x.status);
+                let target = maa.get_target();
+                Some(InstanceHealth {
+                    id,
+                    connected: target.is_some()
+                        && !matches!(
+                            supervisor,
+                            Some(supervisor::Status::Waiting | supervisor::Status::Reconnecting | supervisor::Status::GaveUp)
+                        ),
+                    target,
+                    running: maa.running(),
+                    supervisor,
+                })
+            })
+            .collect()
+    });
+    Readiness {
+        ready: resource.ok && database.ok && core.ok,
+        resource,
+        database,
+        core,
+        instances,
+    }
+}
@@ -0,0 +1,137 @@
+use lazy_static::lazy_static;
+use std::fmt::{Debug, Write};
+use std::sync::{Mutex, PoisonError};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Event, Level, Subscriber,
+};
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{
+    fmt, layer::Context, prelude::*, registry::LookupSpan, reload, EnvFilter, Layer, Registry,
+};
+use crate::{
+    config::{self, Log, LogFormat, Rotation},
+    maa_sys::Maa,
+};
+
+lazy_static! {
+    /// 用于重新加载配置时修改日志级别
+    static ref FILTER: Mutex<Option<reload::Handle<EnvFilter, Registry>>> = Mutex::new(None);
+}
+
+/// 返回的guard在退出前不能释放，否则日志文件中会丢失最后的日志
+pub fn init(log: &Log) -> Option<WorkerGuard> {
+    let filter = EnvFilter::try_new(&log.level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+    *FILTER.lock().unwrap_or_else(PoisonError::into_inner) = Some(handle);
+    let (file, guard) = match &log.file {
+        Some(file) => {
+            let appender = match file.rotation {
+                Rotation::Minutely => rolling::minutely(&file.directory, &file.prefix),
+                Rotation::Hourly => rolling::hourly(&file.directory, &file.prefix),
+                Rotation::Daily => rolling::daily(&file.directory, &file.prefix),
+                Rotation::Never => rolling::never(&file.directory, &file.prefix),
+            };
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(writer), Some(guard))
+        }
+        None => (None, None),
+    };
+    let (stdout, file) = match log.format {
+        LogFormat::Text => (
+            fmt::layer().boxed(),
+            file.map(|writer| fmt::layer().with_ansi(false).with_writer(writer).boxed()),
+        ),
+        LogFormat::Json => (
+            fmt::layer().json().with_current_span(true).with_span_list(true).boxed(),
+            file.map(|writer| {
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_writer(writer)
+                    .boxed()
+            }),
+        ),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(stdout)
+        .with(file)
+        .with(CoreLayer)
+        .init();
+    guard
+}
+
+/// 解析日志级别，不修改当前的级别
+pub fn parse_level(level: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(level).map_err(|e| e.to_string())
+}
+
+/// 替换当前的日志级别
+pub fn set_filter(filter: EnvFilter) -> Result<(), String> {
+    match FILTER.lock().unwrap_or_else(PoisonError::into_inner).as_ref() {
+        Some(handle) => handle.reload(filter).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// 把字段格式化为"message k=v"
+#[derive(Default)]
+struct Visitor(String);
+
+impl Visit for Visitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if !self.0.is_empty() && !self.0.ends_with(' ') {
+            self.0.push(' ');
+        }
+        let _ = match field.name() {
+            "message" => write!(self.0, "{:?}", value),
+            name => write!(self.0, "{}={:?}", name, value),
+        };
+    }
+}
+
+/// 保存在span中的字段
+struct SpanFields(String);
+
+/// 开启log.forward_to_core时把日志写入MaaCore的日志
+struct CoreLayer;
+
+impl<S> Layer<S> for CoreLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = Visitor::default();
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(visitor.0));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !config::current().log.forward_to_core {
+            return;
+        }
+        let mut visitor = Visitor(format!("[server] {}: ", event.metadata().target()));
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                let _ = match span.extensions().get::<SpanFields>() {
+                    Some(fields) => write!(visitor.0, "{}{{{}}}: ", span.name(), fields.0),
+                    None => write!(visitor.0, "{}: ", span.name()),
+                };
+            }
+        }
+        event.record(&mut visitor);
+        let level = match *event.metadata().level() {
+            Level::ERROR => "ERR",
+            Level::WARN => "WRN",
+            Level::INFO => "INF",
+            Level::DEBUG => "DBG",
+            Level::TRACE => "TRC",
+        };
+        let _ = Maa::log(level, &visitor.0);
+    }
+}
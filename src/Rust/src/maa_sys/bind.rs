@@ -82,6 +82,9 @@ extern "C" {
 extern "C" {
     pub fn AsstRunning(handle: AsstHandle) -> AsstBool;
 }
+extern "C" {
+    pub fn AsstBackToHome(handle: AsstHandle) -> AsstBool;
+}
 extern "C" {
     pub fn AsstAsyncConnect(
         handle: AsstHandle,
@@ -24,6 +24,50 @@ impl From<std::str::Utf8Error> for Error {
         Self::Utf8
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AsstMsg {
+    InternalError,
+    InitFailed,
+    ConnectionInfo,
+    AllTasksCompleted,
+    AsyncCallInfo,
+    TaskChainError,
+    TaskChainStart,
+    TaskChainCompleted,
+    TaskChainExtraInfo,
+    TaskChainStopped,
+    SubTaskError,
+    SubTaskStart,
+    SubTaskCompleted,
+    SubTaskExtraInfo,
+    SubTaskStopped,
+}
+
+impl AsstMsg {
+    pub fn from_u32(msg: u32) -> Option<Self> {
+        let msg = match msg {
+            0 => Self::InternalError,
+            1 => Self::InitFailed,
+            2 => Self::ConnectionInfo,
+            3 => Self::AllTasksCompleted,
+            4 => Self::AsyncCallInfo,
+            10000 => Self::TaskChainError,
+            10001 => Self::TaskChainStart,
+            10002 => Self::TaskChainCompleted,
+            10003 => Self::TaskChainExtraInfo,
+            10004 => Self::TaskChainStopped,
+            20000 => Self::SubTaskError,
+            20001 => Self::SubTaskStart,
+            20002 => Self::SubTaskCompleted,
+            20003 => Self::SubTaskExtraInfo,
+            20004 => Self::SubTaskStopped,
+            _ => return None,
+        };
+        Some(msg)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Task {
     pub id: i32,
@@ -31,11 +75,20 @@ pub struct Task {
     pub params: String,
 }
 
+/// 连接时使用的参数，用于重新连接
+#[derive(Debug, Clone)]
+struct ConnectParams {
+    adb_path: String,
+    address: String,
+    config: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct Maa {
     handle: AsstHandle,
     uuid: Option<String>,
     target: Option<String>,
+    connection: Option<ConnectParams>,
     tasks: HashMap<i32, Task>,
 }
 
@@ -47,6 +100,7 @@ impl Maa {
                 handle: AsstCreate(),
                 uuid: None,
                 target: None,
+                connection: None,
                 tasks: HashMap::new(),
             }
         }
@@ -63,6 +117,7 @@ impl Maa {
                 handle: AsstCreateEx(call_back, custom_arg),
                 uuid: None,
                 target: None,
+                connection: None,
                 tasks: HashMap::new(),
             }
         }
@@ -104,7 +159,6 @@ impl Maa {
             Ok(ret)
         }
     }
-    #[allow(dead_code)]
     pub fn set_static_option(option: AsstStaticOptionKey, value: &str) -> Result<(), Error> {
         let c_option_value = std::ffi::CString::new(value)?;
         unsafe {
@@ -115,7 +169,6 @@ impl Maa {
             }
         }
     }
-    #[allow(dead_code)]
     pub fn set_working_directory(path: &str) -> Result<(), Error> {
         let c_path = std::ffi::CString::new(path)?;
         unsafe {
@@ -143,22 +196,51 @@ impl Maa {
         address: &str,
         config: Option<&str>,
     ) -> Result<i32, Error> {
+        let params = ConnectParams {
+            adb_path: adb_path.to_string(),
+            address: address.to_string(),
+            config: config.map(|x| x.to_string()),
+        };
+        self.async_connect(params, true)
+    }
+    /// 不等待连接完成，结果通过AsyncCallInfo回调
+    pub fn request_connect(
+        &mut self,
+        adb_path: &str,
+        address: &str,
+        config: Option<&str>,
+    ) -> Result<i32, Error> {
+        let params = ConnectParams {
+            adb_path: adb_path.to_string(),
+            address: address.to_string(),
+            config: config.map(|x| x.to_string()),
+        };
+        self.async_connect(params, false)
+    }
+    /// 使用上次连接的参数重新连接，不等待连接完成，结果通过AsyncCallInfo回调
+    pub fn reconnect(&mut self) -> Result<i32, Error> {
+        let params = self.connection.clone().ok_or(Error::Unknown)?;
+        self.async_connect(params, false)
+    }
+    fn async_connect(&mut self, params: ConnectParams, block: bool) -> Result<i32, Error> {
+        let c_adb_path = std::ffi::CString::new(params.adb_path.as_str())?;
+        let c_address = std::ffi::CString::new(params.address.as_str())?;
+        let c_config = params.config.as_deref().map(std::ffi::CString::new).transpose()?;
+        let c_cfg_ptr = match &c_config {
+            Some(cfg) => cfg.as_ptr(),
+            None => std::ptr::null(),
+        };
         unsafe {
-            let c_adb_path = std::ffi::CString::new(adb_path)?;
-            let c_address = std::ffi::CString::new(address)?;
-            let c_cfg_ptr = match config {
-                Some(cfg) => std::ffi::CString::new(cfg)?.as_ptr(),
-                None => 0 as *const std::os::raw::c_char,
-            };
             let ret = AsstAsyncConnect(
                 self.handle,
                 c_adb_path.as_ptr(),
                 c_address.as_ptr(),
                 c_cfg_ptr,
-                1,
+                block as AsstBool,
             );
             if ret != 0 {
-                self.target = Some(address.to_string());
+                self.target = Some(params.address.clone());
+                self.connection = Some(params);
                 Ok(ret)
             } else {
                 Err(Error::Unknown)
@@ -194,10 +276,17 @@ impl Maa {
             }
         }
     }
-    #[allow(dead_code)]
     pub fn running(&self) -> bool {
         unsafe { AsstRunning(self.handle) == 1 }
     }
+    pub fn back_to_home(&self) -> Result<(), Error> {
+        unsafe {
+            match AsstBackToHome(self.handle) {
+                1 => Ok(()),
+                _ => Err(Error::Unknown),
+            }
+        }
+    }
     pub fn click(&self, x: i32, y: i32) -> Result<i32, Error> {
         unsafe {
             let ret = AsstAsyncClick(self.handle, x, y, 0);
@@ -231,6 +320,15 @@ impl Maa {
             }
         }
     }
+    /// 不等待截图完成，结果通过AsyncCallInfo回调，之后用screenshot读取
+    pub fn request_screenshot(&mut self) -> Result<i32, Error> {
+        unsafe {
+            match AsstAsyncScreencap(self.handle, 0) {
+                0 => Err(Error::Unknown),
+                call_id => Ok(call_id),
+            }
+        }
+    }
     #[allow(dead_code)]
     pub fn take_screenshot(&mut self) -> Result<(), Error> {
         unsafe {
@@ -256,11 +354,16 @@ impl Maa {
             Ok(task_id)
         }
     }
-    pub fn set_task(&self, id: i32, params: &str) -> Result<(), Error> {
+    pub fn set_task(&mut self, id: i32, params: &str) -> Result<(), Error> {
         unsafe {
             let c_params = std::ffi::CString::new(params)?;
             match AsstSetTaskParams(self.handle, id, c_params.as_ptr()) {
-                1 => Ok(()),
+                1 => {
+                    if let Some(task) = self.tasks.get_mut(&id) {
+                        task.params = params.to_string();
+                    }
+                    Ok(())
+                }
                 _ => Err(Error::Unknown),
             }
         }
@@ -333,7 +436,6 @@ impl Maa {
             }
         }
     }
-    #[allow(dead_code)]
     pub fn log(level_str: &str, message: &str) -> Result<(), Error> {
         let c_level_str = std::ffi::CString::new(level_str)?;
         let c_message = std::ffi::CString::new(message)?;
@@ -2,30 +2,85 @@ mod maa_sys;
 mod database;
 mod api;
 mod config;
-use config::CONFIG;
-use std::sync::Mutex;
-use maa_sys::Maa;
-use actix_web::{middleware, rt, App, HttpServer, web};
+mod callback;
+mod resource;
+mod scheduler;
+mod supervisor;
+mod watchdog;
+mod flow;
+mod reload;
+mod auth;
+mod tls;
+mod metrics;
+mod health;
+mod shutdown;
+mod logging;
+use std::time::Instant;
+use actix_web::{dev::Service, middleware, rt, App, HttpServer};
 
 const SERVER_VERSION:&str="v0.0.1";
 fn main() -> std::io::Result<()> {
-    Maa::load_resource(&CONFIG.resource.path).unwrap();
-    if CONFIG.database.drop_on_start_up{
-        database::msg::drop_all().unwrap();
+    if let Some(config::Command::CheckConfig) = config::ARGS.command {
+        match config::load(&config::ARGS) {
+            Ok(config) => println!("{}", serde_json::to_string_pretty(&config.redacted()).unwrap()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
     }
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
-    let maa_manager = web::Data::new(Mutex::new(api::MaaManager::new()));
-    rt::System::new().block_on(async {
-        HttpServer::new(move|| {
+    let config = config::current();
+    let _log_guard = logging::init(&config.log);
+    // 出错时继续启动，由/readyz报告
+    if let Err(e) = health::setup_core(&config.core) {
+        tracing::error!("{}", e);
+    }
+    if let Err(e) = health::load_resource(&config.resource.path) {
+        tracing::error!("{}", e);
+    }
+    if let Err(e) = health::migrate() {
+        tracing::error!("{}", e);
+    }
+    if config.database.drop_on_start_up{
+        if let Err(e) = database::msg::drop_all() {
+            tracing::error!("failed to drop messages: {:?}", e);
+        }
+    }
+    scheduler::start();
+    supervisor::start();
+    watchdog::start();
+    flow::start();
+    reload::start();
+    let maa_manager = api::MAA_MANAGER.clone();
+    let ret = rt::System::new().block_on(async {
+        let server = HttpServer::new(move|| {
             App::new()
                 .app_data(maa_manager.clone())
+                .wrap(auth::Auth)
+                .wrap_fn(|req, srv| {
+                    let start = Instant::now();
+                    let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+                    let response = srv.call(req);
+                    async move {
+                        let response = response.await?;
+                        metrics::observe_request(&route, response.status().as_u16(), start.elapsed());
+                        Ok(response)
+                    }
+                })
                 .wrap(middleware::Logger::default())
                 .configure(api::config)
-        })
-        .bind((CONFIG.server.address.clone(), CONFIG.server.port))?
-        .run()
-        .await
-    })
+        });
+        let address = (config.server.address.clone(), config.server.port);
+        let server = match &config.server.tls {
+            Some(tls) => {
+                let tls = tls::server_config(tls).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+                server.bind_rustls(address, tls)?
+            }
+            None => server.bind(address)?,
+        };
+        server.run().await
+    });
+    shutdown::run();
+    ret
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use crate::{api::MaaManager, config, maa_sys::AsstMsg, supervisor};
+
+/// 直方图的上界，单位为秒
+const BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    /// 每个上界内的次数，不累加
+    counts: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|x| seconds <= *x) {
+            self.counts[i] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, sep, bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, sep, self.count);
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    tasks_appended: BTreeMap<String, u64>,
+    tasks_started: BTreeMap<String, u64>,
+    tasks_completed: BTreeMap<String, u64>,
+    task_chain_errors: BTreeMap<String, u64>,
+    callbacks: BTreeMap<String, u64>,
+    /// 按来源区分
+    screenshots: BTreeMap<&'static str, Histogram>,
+    /// 按路由和状态码区分
+    requests: BTreeMap<(String, u16), Histogram>,
+}
+
+lazy_static! {
+    static ref METRICS: Mutex<Metrics> = Mutex::new(Metrics::default());
+}
+
+fn metrics() -> std::sync::MutexGuard<'static, Metrics> {
+    METRICS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn increase(map: &mut BTreeMap<String, u64>, key: &str) {
+    *map.entry(key.to_string()).or_default() += 1;
+}
+
+pub fn task_appended(type_: &str) {
+    increase(&mut metrics().tasks_appended, type_);
+}
+
+/// 在写入线程中调用
+pub fn on_callback(type_: AsstMsg, value: &Value) {
+    let mut metrics = metrics();
+    increase(&mut metrics.callbacks, &format!("{:?}", type_));
+    let taskchain = value.get("taskchain").and_then(Value::as_str).unwrap_or_default();
+    match type_ {
+        AsstMsg::TaskChainStart => increase(&mut metrics.tasks_started, taskchain),
+        AsstMsg::TaskChainCompleted => increase(&mut metrics.tasks_completed, taskchain),
+        AsstMsg::TaskChainError => increase(&mut metrics.task_chain_errors, taskchain),
+        _ => {}
+    }
+}
+
+pub fn observe_screenshot(source: &'static str, duration: Duration) {
+    metrics().screenshots.entry(source).or_default().observe(duration);
+}
+
+pub fn observe_request(route: &str, status: u16, duration: Duration) {
+    metrics()
+        .requests
+        .entry((route.to_string(), status))
+        .or_default()
+        .observe(duration);
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn write_header(out: &mut String, name: &str, type_: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, type_);
+}
+
+fn write_counters(out: &mut String, name: &str, help: &str, label: &str, values: &BTreeMap<String, u64>) {
+    write_header(out, name, "counter", help);
+    for (key, value) in values {
+        let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, escape(key), value);
+    }
+}
+
+/// 目录下所有文件的大小
+fn dir_size(path: &Path) -> u64 {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// 实例的状态，断开时使用自动重连的状态
+fn instance_state(manager: &MaaManager, id: i64) -> &'static str {
+    match supervisor::get(id).map(|x| x.status) {
+        Some(supervisor::Status::Waiting) | Some(supervisor::Status::Reconnecting) => "reconnecting",
+        Some(supervisor::Status::GaveUp) => "disconnected",
+        _ if manager.get(id).is_some_and(|maa| maa.running()) => "running",
+        _ => "idle",
+    }
+}
+
+/// Prometheus文本格式
+pub fn render(manager: &MaaManager) -> String {
+    let mut out = String::new();
+    let mut states: BTreeMap<&str, u64> = ["idle", "running", "reconnecting", "disconnected"]
+        .into_iter()
+        .map(|x| (x, 0))
+        .collect();
+    for id in manager.get_all_id() {
+        *states.entry(instance_state(manager, id)).or_default() += 1;
+    }
+    write_header(&mut out, "maa_instances", "gauge", "Number of instances by state.");
+    for (state, count) in states {
+        let _ = writeln!(out, "maa_instances{{state=\"{}\"}} {}", state, count);
+    }
+    let metrics = metrics();
+    write_counters(&mut out, "maa_tasks_appended_total", "Tasks appended to instances.", "type", &metrics.tasks_appended);
+    write_counters(&mut out, "maa_tasks_started_total", "Task chains started.", "type", &metrics.tasks_started);
+    write_counters(&mut out, "maa_tasks_completed_total", "Task chains completed.", "type", &metrics.tasks_completed);
+    write_counters(&mut out, "maa_task_chain_errors_total", "TaskChainError messages.", "type", &metrics.task_chain_errors);
+    write_counters(&mut out, "maa_callbacks_total", "Callback messages received.", "msg", &metrics.callbacks);
+    write_header(&mut out, "maa_screenshot_duration_seconds", "histogram", "Time taken to take a screenshot.");
+    for (source, histogram) in &metrics.screenshots {
+        histogram.write(&mut out, "maa_screenshot_duration_seconds", &format!("source=\"{}\"", source));
+    }
+    write_header(&mut out, "maa_http_request_duration_seconds", "histogram", "HTTP request latency by route.");
+    for ((route, status), histogram) in &metrics.requests {
+        let labels = format!("route=\"{}\",status=\"{}\"", escape(route), status);
+        histogram.write(&mut out, "maa_http_request_duration_seconds", &labels);
+    }
+    write_header(&mut out, "maa_database_size_bytes", "gauge", "Size of the database directory on disk.");
+    let _ = writeln!(out, "maa_database_size_bytes {}", dir_size(Path::new(&config::current().database.path)));
+    out
+}
@@ -0,0 +1,191 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+use crate::{
+    config::{self, ConfigError},
+    health,
+    logging,
+    resource,
+};
+
+const TICK: Duration = Duration::from_secs(2);
+/// 修改后需要重启才能生效的字段
+const RESTART_REQUIRED: &[&str] = &[
+    "server.address",
+    "server.port",
+    "server.tls",
+    "database.path",
+    "database.drop_on_start_up",
+    "database.queue_capacity",
+    "core.user_dir",
+    "core.static_options",
+    "log.format",
+    "log.file",
+];
+
+#[derive(Debug, Default, Serialize)]
+pub struct Reload {
+    /// 已生效的字段
+    pub applied: Vec<String>,
+    /// 已修改但需要重启才能生效的字段
+    pub restart_required: Vec<String>,
+}
+
+lazy_static! {
+    /// 文件监视、SIGHUP和接口可能同时重新加载
+    static ref RELOADING: Mutex<()> = Mutex::new(());
+}
+
+pub fn start() {
+    std::thread::Builder::new()
+        .name("config".to_string())
+        .spawn(|| {
+            let path = config::path(&config::ARGS);
+            let mut modified = modified_time(&path);
+            loop {
+                std::thread::sleep(TICK);
+                let time = modified_time(&path);
+                if time == modified {
+                    continue;
+                }
+                modified = time;
+                if config::current().server.watch_config {
+                    reload_and_log("config file changed");
+                }
+            }
+        })
+        .unwrap();
+    #[cfg(unix)]
+    {
+        let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP]).unwrap();
+        std::thread::Builder::new()
+            .name("sighup".to_string())
+            .spawn(move || {
+                for _ in signals.forever() {
+                    reload_and_log("SIGHUP");
+                }
+            })
+            .unwrap();
+    }
+}
+
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+fn reload_and_log(trigger: &str) {
+    match reload() {
+        Ok(result) => {
+            if !result.applied.is_empty() {
+                tracing::info!("reloaded config ({}), applied: {}", trigger, result.applied.join(", "));
+            }
+            if !result.restart_required.is_empty() {
+                tracing::warn!("changes to {} require a restart", result.restart_required.join(", "));
+            }
+        }
+        Err(e) => tracing::warn!("failed to reload config ({}): {}", trigger, e),
+    }
+}
+
+/// 重新读取配置文件，出错时保留原来的配置
+pub fn reload() -> Result<Reload, ConfigError> {
+    let _reloading = RELOADING.lock().unwrap_or_else(PoisonError::into_inner);
+    let old = config::current();
+    let mut new = config::load(&config::ARGS)?;
+    let changed = diff(
+        &serde_json::to_value(&*old).unwrap_or_default(),
+        &serde_json::to_value(&new).unwrap_or_default(),
+    );
+    // 需要重启的字段保持运行中的值，之后的重新加载仍会报告
+    new.server.address = old.server.address.clone();
+    new.server.port = old.server.port;
+    new.server.tls = old.server.tls.clone();
+    new.database.path = old.database.path.clone();
+    new.database.drop_on_start_up = old.database.drop_on_start_up;
+    new.database.queue_capacity = old.database.queue_capacity;
+    new.core.user_dir = old.core.user_dir.clone();
+    new.core.static_options = old.core.static_options.clone();
+    new.log.format = old.log.format;
+    new.log.file = old.log.file.clone();
+    // 先检查所有修改，全部通过后再一起生效，失败时不留下部分修改
+    let filter = if new.log.level != old.log.level {
+        let filter = logging::parse_level(&new.log.level)
+            .map_err(|e| ConfigError::Invalid(vec![format!("log.level: {}", e)]))?;
+        Some(filter)
+    } else {
+        None
+    };
+    let resource_changed = new.resource.path != old.resource.path;
+    if resource_changed {
+        health::load_resource(&new.resource.path).map_err(|_| ConfigError::Resource(new.resource.path.clone()))?;
+    }
+    if let Some(filter) = filter {
+        logging::set_filter(filter).map_err(|e| ConfigError::Invalid(vec![format!("log.level: {}", e)]))?;
+    }
+    config::replace(new);
+    if resource_changed {
+        resource::reload();
+    }
+    let (restart_required, applied) = changed
+        .into_iter()
+        .partition(|x| RESTART_REQUIRED.contains(&x.as_str()));
+    Ok(Reload {
+        applied,
+        restart_required,
+    })
+}
+
+/// 返回有变化的字段，如server.port，不是对象的段整体比较
+fn diff(old: &Value, new: &Value) -> Vec<String> {
+    let (old, new) = match (old.as_object(), new.as_object()) {
+        (Some(old), Some(new)) => (old, new),
+        _ => return Vec::new(),
+    };
+    let mut changed = Vec::new();
+    for (section, value) in new {
+        match (old.get(section), value) {
+            (Some(Value::Object(old_fields)), Value::Object(fields)) => {
+                for (field, value) in fields {
+                    if old_fields.get(field) != Some(value) {
+                        changed.push(format!("{}.{}", section, field));
+                    }
+                }
+            }
+            (old_value, value) => {
+                if old_value != Some(value) {
+                    changed.push(section.clone());
+                }
+            }
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_reports_changed_fields() {
+        let old = json!({"server": {"port": 11451, "address": "127.0.0.1"}, "log": {"level": "info"}});
+        let new = json!({"server": {"port": 8080, "address": "127.0.0.1"}, "log": {"level": "debug"}});
+        assert_eq!(diff(&old, &new), ["log.level", "server.port"]);
+    }
+
+    #[test]
+    fn diff_reports_added_fields_and_whole_sections() {
+        let old = json!({"server": {"port": 11451}, "schedules": []});
+        let new = json!({"server": {"port": 11451, "tls": null}, "schedules": [{"name": "daily"}], "auth": {"keys": []}});
+        assert_eq!(diff(&old, &new), ["auth", "schedules", "server.tls"]);
+    }
+
+    #[test]
+    fn diff_of_equal_configs_is_empty() {
+        let config = json!({"server": {"port": 11451}, "schedules": []});
+        assert!(diff(&config, &config).is_empty());
+        assert!(diff(&json!(null), &config).is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+use crate::config;
+
+/// 从资源目录读取的数据，资源路径修改后重新读取
+struct Tables {
+    item_names: HashMap<String, String>,
+    operators: HashMap<String, Operator>,
+}
+
+lazy_static! {
+    static ref TABLES: RwLock<Arc<Tables>> = RwLock::new(Arc::new(Tables::load()));
+}
+
+impl Tables {
+    fn load() -> Self {
+        let mut item_names = HashMap::new();
+        if let Some(Value::Object(items)) = load("item_index.json") {
+            for (id, item) in items {
+                if let Some(name) = item.get("name").and_then(Value::as_str) {
+                    item_names.insert(id, name.to_string());
+                }
+            }
+        }
+        let operators = load("battle_data.json")
+            .and_then(|mut v| serde_json::from_value(v["chars"].take()).ok())
+            .unwrap_or_default();
+        Tables { item_names, operators }
+    }
+}
+
+fn tables() -> Arc<Tables> {
+    TABLES.read().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+pub fn reload() {
+    *TABLES.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(Tables::load());
+}
+
+/// battle_data.json中的干员信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operator {
+    pub name: String,
+    pub name_en: Option<String>,
+    pub profession: Option<String>,
+    pub position: Option<String>,
+    pub rarity: Option<i64>,
+}
+
+/// 读取资源目录下resource文件夹内的json文件
+fn load(name: &str) -> Option<Value> {
+    let mut p = PathBuf::new();
+    p.push(config::current().resource.path.clone());
+    p.push("resource");
+    p.push(name);
+    let value = fs::read(&p)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_slice(&s).map_err(|e| e.to_string()));
+    match value {
+        Ok(value) => Some(value),
+        Err(e) => {
+            tracing::warn!("failed to load {}: {}", p.display(), e);
+            None
+        }
+    }
+}
+
+pub fn item_name(id: &str) -> Option<String> {
+    tables().item_names.get(id).cloned()
+}
+
+pub fn operator(id: &str) -> Option<Operator> {
+    tables().operators.get(id).cloned()
+}
@@ -0,0 +1,277 @@
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use crate::{
+    api::MAA_MANAGER,
+    callback,
+    config::{self, Connection, OnBusy, Schedule},
+    database::{self, schedule::Outcome},
+    shutdown,
+};
+
+const TICK: Duration = Duration::from_secs(1);
+/// 等待连接完成的最长时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 计划在内存中的状态
+struct State {
+    /// 上次检查的时间，之后到点的触发才会执行
+    checked: DateTime<Utc>,
+    /// 因实例正在运行而排队的触发
+    queued: Option<i64>,
+    /// 按connection创建的实例
+    instance: Option<i64>,
+}
+
+lazy_static! {
+    static ref STATES: Mutex<HashMap<String, State>> = Mutex::new(HashMap::new());
+}
+
+pub fn start() {
+    std::thread::Builder::new()
+        .name("scheduler".to_string())
+        .spawn(|| loop {
+            tick(Utc::now());
+            std::thread::sleep(TICK);
+        })
+        .unwrap();
+}
+
+/// 解析cron表达式和时区，5段的表达式视为省略了秒
+fn parse(schedule: &Schedule) -> Result<(cron::Schedule, Tz), String> {
+    let expression = if schedule.cron.split_whitespace().count() == 5 {
+        format!("0 {}", schedule.cron)
+    } else {
+        schedule.cron.clone()
+    };
+    let cron = cron::Schedule::from_str(&expression).map_err(|e| format!("invalid cron expression: {}", e))?;
+    let tz = Tz::from_str(&schedule.timezone).map_err(|e| format!("invalid timezone: {}", e))?;
+    Ok((cron, tz))
+}
+
+pub fn validate(schedule: &Schedule) -> Result<(), String> {
+    if database::schedule::reserved(&schedule.name) {
+        return Err("invalid name".to_string());
+    }
+    if schedule.instance.is_none() && schedule.connection.is_none() {
+        return Err("either instance or connection is required".to_string());
+    }
+    parse(schedule).map(|_| ())
+}
+
+/// 配置文件中的计划在前，名称相同时忽略数据库中的计划
+pub fn schedules() -> Vec<Schedule> {
+    let mut result = config::current().schedules.clone();
+    match database::schedule::all() {
+        Ok(saved) => {
+            for schedule in saved {
+                if !result.iter().any(|x| x.name == schedule.name) {
+                    result.push(schedule);
+                }
+            }
+        }
+        Err(e) => tracing::warn!("failed to load schedules: {:?}", e),
+    }
+    result
+}
+
+/// 之后nums次触发的时间
+pub fn upcoming(schedule: &Schedule, nums: usize) -> Vec<i64> {
+    match parse(schedule) {
+        Ok((cron, tz)) => cron
+            .upcoming(tz)
+            .take(nums)
+            .map(|t| t.timestamp_millis())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn tick(now: DateTime<Utc>) {
+    if shutdown::started() {
+        return;
+    }
+    let schedules = schedules();
+    let mut states = STATES.lock().unwrap_or_else(PoisonError::into_inner);
+    states.retain(|name, _| schedules.iter().any(|x| x.name == *name));
+    for schedule in schedules.iter().filter(|x| x.enabled) {
+        let (cron, tz) = match parse(schedule) {
+            Ok(parsed) => parsed,
+            Err(_) => continue,
+        };
+        let state = states.entry(schedule.name.clone()).or_insert(State {
+            checked: now,
+            queued: None,
+            instance: None,
+        });
+        // 同一次检查中错过的多次触发只执行一次
+        let due = cron
+            .after(&tz.from_utc_datetime(&state.checked.naive_utc()))
+            .next()
+            .filter(|t| t.with_timezone(&Utc) <= now)
+            .map(|t| t.timestamp_millis());
+        state.checked = now;
+        match (due, state.queued) {
+            (Some(scheduled), Some(_)) => {
+                record(schedule, scheduled, Outcome::Skipped, None, None, Some("a firing is already queued".to_string()));
+            }
+            (Some(scheduled), None) => fire(schedule, state, scheduled),
+            (None, Some(scheduled)) => fire(schedule, state, scheduled),
+            (None, None) => {}
+        }
+    }
+}
+
+fn record(
+    schedule: &Schedule,
+    scheduled: i64,
+    outcome: Outcome,
+    instance: Option<i64>,
+    run_id: Option<u64>,
+    reason: Option<String>,
+) {
+    if let Err(e) = database::schedule::record(&schedule.name, scheduled, outcome, instance, run_id, reason) {
+        tracing::warn!("failed to record firing of schedule {}: {:?}", schedule.name, e);
+    }
+}
+
+enum Fired {
+    Started(i64, u64),
+    Busy(i64),
+    Failed(Option<i64>, String),
+}
+
+fn fire(schedule: &Schedule, state: &mut State, scheduled: i64) {
+    let _span = tracing::info_span!("schedule", name = %schedule.name).entered();
+    let was_queued = state.queued.take().is_some();
+    match run(schedule, state) {
+        Fired::Started(instance, run_id) => {
+            tracing::info!("schedule {} started run {} on instance {}", schedule.name, run_id, instance);
+            record(schedule, scheduled, Outcome::Started, Some(instance), Some(run_id), None);
+        }
+        Fired::Busy(instance) => match schedule.on_busy {
+            OnBusy::Queue => {
+                state.queued = Some(scheduled);
+                // 排队中的触发每次检查都会重试，只记录一次
+                if !was_queued {
+                    record(schedule, scheduled, Outcome::Queued, Some(instance), None, None);
+                }
+            }
+            OnBusy::Skip => {
+                record(schedule, scheduled, Outcome::Skipped, Some(instance), None, Some("instance is running".to_string()));
+            }
+        },
+        Fired::Failed(instance, reason) => {
+            tracing::warn!("schedule {} failed: {}", schedule.name, reason);
+            record(schedule, scheduled, Outcome::Failed, instance, None, Some(reason));
+        }
+    }
+}
+
+fn run(schedule: &Schedule, state: &mut State) -> Fired {
+    let id = match (schedule.instance, &schedule.connection) {
+        (Some(id), _) => id,
+        (None, Some(connection)) => match connect(connection, state) {
+            Ok(id) => id,
+            Err(reason) => return Fired::Failed(None, reason),
+        },
+        (None, None) => return Fired::Failed(None, "no instance or connection".to_string()),
+    };
+    let mut manager = match MAA_MANAGER.lock() {
+        Ok(manager) => manager,
+        Err(_) => return Fired::Failed(Some(id), "maa manager is poisoned".to_string()),
+    };
+    let maa = match manager.get(id) {
+        Some(maa) => maa,
+        None => return Fired::Failed(Some(id), "instance not found".to_string()),
+    };
+    if maa.running() {
+        return Fired::Busy(id);
+    }
+    match manager.start_tasks(id, &schedule.tasks) {
+        Ok(run_id) => Fired::Started(id, run_id),
+        Err(e) => Fired::Failed(Some(id), format!("failed to start: {:?}", e)),
+    }
+}
+
+/// 返回按connection创建的实例，没有时创建并连接，连接时不持有MAA_MANAGER
+fn connect(connection: &Connection, state: &mut State) -> Result<i64, String> {
+    let id = {
+        let mut manager = MAA_MANAGER.lock().map_err(|_| "maa manager is poisoned".to_string())?;
+        if let Some(id) = state.instance.filter(|id| manager.get(*id).is_some()) {
+            return Ok(id);
+        }
+        manager.create()
+    };
+    let config = match connection.config {
+        serde_json::Value::Null => None,
+        ref config => Some(config.to_string()),
+    };
+    let result = callback::call_async(id, CONNECT_TIMEOUT, |maa| {
+        maa.request_connect(&connection.adb_path, &connection.target, config.as_deref())
+    });
+    // 连接失败时删除实例，下次触发重新创建并连接
+    if let Err(e) = result {
+        if let Ok(mut manager) = MAA_MANAGER.lock() {
+            manager.delete(id);
+        }
+        return Err(format!("failed to connect: {}", e));
+    }
+    state.instance = Some(id);
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schedule(cron: &str, timezone: &str) -> Schedule {
+        serde_json::from_value(json!({
+            "name": "daily",
+            "cron": cron,
+            "timezone": timezone,
+            "instance": 1,
+            "tasks": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_accepts_five_field_expressions_in_timezone() {
+        let (cron, tz) = parse(&schedule("0 4 * * *", "Asia/Shanghai")).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap().with_timezone(&tz);
+        let next = cron.after(&now).next().unwrap();
+        // 上海04:00为UTC 20:00
+        assert_eq!(next.with_timezone(&Utc), Utc.with_ymd_and_hms(2024, 1, 1, 20, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn parse_accepts_expressions_with_seconds() {
+        let (cron, tz) = parse(&schedule("30 0 12 * * *", "UTC")).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap().with_timezone(&tz);
+        let next = cron.after(&now).next().unwrap();
+        assert_eq!(next.with_timezone(&Utc), Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 30).unwrap());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_values() {
+        assert!(parse(&schedule("not a cron", "UTC")).unwrap_err().starts_with("invalid cron expression"));
+        assert!(parse(&schedule("0 4 * * *", "Mars/Olympus")).unwrap_err().starts_with("invalid timezone"));
+    }
+
+    #[test]
+    fn validate_checks_name_and_target() {
+        let mut reserved = schedule("0 4 * * *", "UTC");
+        reserved.name = "__schedules".to_string();
+        assert!(validate(&reserved).is_err());
+        let mut no_target = schedule("0 4 * * *", "UTC");
+        no_target.instance = None;
+        assert!(validate(&no_target).is_err());
+        assert!(validate(&schedule("0 4 * * *", "UTC")).is_ok());
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+use crate::{api::MAA_MANAGER, config, database, flow};
+
+const POLL: Duration = Duration::from_millis(100);
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// 开始关闭后后台线程不再开始新的运行
+pub fn started() -> bool {
+    STARTED.load(Ordering::SeqCst)
+}
+
+/// http服务停止后调用，停止所有实例并写入数据后再销毁句柄
+pub fn run() {
+    STARTED.store(true, Ordering::SeqCst);
+    let timeout = Duration::from_millis(config::current().server.shutdown_timeout);
+    let deadline = Instant::now() + timeout;
+    tracing::info!("shutting down");
+    let mut manager = MAA_MANAGER.lock().unwrap_or_else(PoisonError::into_inner);
+    flow::shutdown();
+    let running: Vec<i64> = manager
+        .get_all_id()
+        .into_iter()
+        .filter(|id| manager.get(*id).is_some_and(|maa| maa.running()))
+        .collect();
+    for id in &running {
+        if let Err(e) = manager.stop(*id, "server shutting down") {
+            tracing::warn!("failed to stop instance {}: {:?}", id, e);
+        }
+    }
+    let is_running = |id: &i64| manager.get(*id).is_some_and(|maa| maa.running());
+    while running.iter().any(is_running) && Instant::now() < deadline {
+        std::thread::sleep(POLL);
+    }
+    let still_running: Vec<i64> = running.iter().copied().filter(is_running).collect();
+    if !still_running.is_empty() {
+        tracing::warn!("instances {:?} did not stop within {}ms", still_running, timeout.as_millis());
+    }
+    // 停止时的回调也需要写入
+    let remaining = deadline.saturating_duration_since(Instant::now()).max(Duration::from_secs(1));
+    if !database::writer::flush(remaining) {
+        tracing::warn!("failed to flush pending messages before exit");
+    }
+    database::flush_all();
+    manager.destroy_all();
+    tracing::info!("shutdown complete");
+}
@@ -0,0 +1,240 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use crate::{
+    api::{Error, MaaManager, MAA_MANAGER},
+    config::{self, TaskSpec},
+    database::run,
+    maa_sys::AsstMsg,
+    shutdown,
+};
+
+const TICK: Duration = Duration::from_millis(500);
+/// 每个实例保留的重连记录数
+const HISTORY_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Connected,
+    /// 等待下一次重连
+    Waiting,
+    Reconnecting,
+    /// 连续失败次数达到上限
+    GaveUp,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Attempt {
+    pub time: i64,
+    pub attempt: u32,
+    pub success: bool,
+    pub error: Option<String>,
+    /// 重连成功后重新运行时的运行记录id
+    pub run_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct State {
+    pub status: Status,
+    /// 触发重连的消息
+    pub trigger: Option<String>,
+    pub disconnected: Option<i64>,
+    /// 本次断开后失败的次数
+    pub attempts: u32,
+    pub next_attempt: Option<i64>,
+    /// 断开时未完成的任务
+    pub interrupted_tasks: Vec<TaskSpec>,
+    pub history: VecDeque<Attempt>,
+    #[serde(skip)]
+    call_id: Option<i32>,
+    #[serde(skip)]
+    restart: bool,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            status: Status::Connected,
+            trigger: None,
+            disconnected: None,
+            attempts: 0,
+            next_attempt: None,
+            interrupted_tasks: Vec::new(),
+            history: VecDeque::new(),
+            call_id: None,
+            restart: false,
+        }
+    }
+    fn push(&mut self, attempt: Attempt) {
+        if self.history.len() >= HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(attempt);
+    }
+    /// 按失败次数计算下一次重连的时间
+    fn schedule(&mut self, now: i64) {
+        let config = &config::current().supervisor;
+        if config.max_attempts != 0 && self.attempts >= config.max_attempts {
+            self.status = Status::GaveUp;
+            self.next_attempt = None;
+            return;
+        }
+        let delay = config
+            .initial_delay
+            .saturating_mul(1u64.checked_shl(self.attempts).unwrap_or(u64::MAX))
+            .min(config.max_delay);
+        self.status = Status::Waiting;
+        self.next_attempt = Some(now + delay as i64);
+    }
+    fn failed(&mut self, now: i64, error: String) {
+        self.attempts += 1;
+        self.call_id = None;
+        self.push(Attempt {
+            time: now,
+            attempt: self.attempts,
+            success: false,
+            error: Some(error),
+            run_id: None,
+        });
+        self.schedule(now);
+    }
+}
+
+lazy_static! {
+    static ref STATES: Mutex<HashMap<i64, State>> = Mutex::new(HashMap::new());
+}
+
+pub fn start() {
+    std::thread::Builder::new()
+        .name("supervisor".to_string())
+        .spawn(|| loop {
+            tick();
+            std::thread::sleep(TICK);
+        })
+        .unwrap();
+}
+
+pub fn get(instance: i64) -> Option<State> {
+    let states = STATES.lock().unwrap_or_else(PoisonError::into_inner);
+    states.get(&instance).cloned()
+}
+
+pub fn all() -> HashMap<i64, State> {
+    STATES.lock().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+/// 在写入线程中调用，不能访问MAA_MANAGER
+pub fn on_callback(instance: i64, type_: AsstMsg, value: &Value) {
+    let what = value.get("what").and_then(Value::as_str).unwrap_or_default();
+    let now = chrono::Local::now().timestamp_millis();
+    let mut states = STATES.lock().unwrap_or_else(PoisonError::into_inner);
+    match type_ {
+        AsstMsg::ConnectionInfo if config::current().supervisor.triggers.iter().any(|x| x == what) => {
+            let state = states.entry(instance).or_insert_with(State::new);
+            // 重连过程中的断开消息由重连结果处理
+            if matches!(state.status, Status::Waiting | Status::Reconnecting) {
+                return;
+            }
+            state.trigger = match value.get("why").and_then(Value::as_str) {
+                Some(why) if !why.is_empty() => Some(format!("{}: {}", what, why)),
+                _ => Some(what.to_string()),
+            };
+            state.disconnected = Some(now);
+            state.attempts = 0;
+            state.restart = false;
+            state.interrupted_tasks = run::current(instance).map(|run| run.unfinished()).unwrap_or_default();
+            tracing::warn!("instance {} disconnected ({}), reconnecting", instance, what);
+            state.schedule(now);
+        }
+        AsstMsg::AsyncCallInfo if what == "Connect" => {
+            let state = match states.get_mut(&instance) {
+                Some(state) => state,
+                None => return,
+            };
+            let call_id = value.get("async_call_id").and_then(Value::as_i64);
+            if state.status != Status::Reconnecting || call_id != state.call_id.map(|x| x as i64) {
+                return;
+            }
+            let ret = value
+                .get("details")
+                .and_then(|x| x.get("ret"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            if !ret {
+                state.failed(now, "connect failed".to_string());
+                return;
+            }
+            tracing::info!("instance {} reconnected after {} failed attempts", instance, state.attempts);
+            state.status = Status::Connected;
+            state.call_id = None;
+            state.next_attempt = None;
+            state.restart = config::current().supervisor.restart_tasks && !state.interrupted_tasks.is_empty();
+            state.push(Attempt {
+                time: now,
+                attempt: state.attempts + 1,
+                success: true,
+                error: None,
+                run_id: None,
+            });
+            state.attempts = 0;
+        }
+        _ => {}
+    }
+}
+
+fn tick() {
+    if !config::current().supervisor.enabled || shutdown::started() {
+        return;
+    }
+    let mut manager = match MAA_MANAGER.lock() {
+        Ok(manager) => manager,
+        Err(_) => return,
+    };
+    // 持有STATES直到记下call_id，避免重连结果先于call_id被处理
+    let mut states = STATES.lock().unwrap_or_else(PoisonError::into_inner);
+    states.retain(|id, _| manager.get(*id).is_some());
+    let now = chrono::Local::now().timestamp_millis();
+    for (id, state) in states.iter_mut() {
+        let _span = tracing::info_span!("supervisor", instance = *id).entered();
+        if state.status == Status::Waiting && state.next_attempt.is_some_and(|t| t <= now) {
+            let maa = match manager.get_mut(*id) {
+                Some(maa) => maa,
+                None => continue,
+            };
+            match maa.reconnect() {
+                Ok(call_id) => {
+                    state.status = Status::Reconnecting;
+                    state.next_attempt = None;
+                    state.call_id = Some(call_id);
+                }
+                Err(e) => state.failed(now, format!("{:?}", e)),
+            }
+        }
+        if state.restart {
+            state.restart = false;
+            let tasks = std::mem::take(&mut state.interrupted_tasks);
+            match restart(&mut manager, *id, &tasks) {
+                Ok(run_id) => {
+                    tracing::info!("instance {} restarted {} interrupted tasks as run {}", id, tasks.len(), run_id);
+                    if let Some(attempt) = state.history.back_mut() {
+                        attempt.run_id = Some(run_id);
+                    }
+                }
+                Err(e) => tracing::warn!("failed to restart interrupted tasks of instance {}: {:?}", id, e),
+            }
+        }
+    }
+}
+
+/// 停止残留的任务，重新添加断开时未完成的任务并开始运行
+fn restart(manager: &mut MaaManager, id: i64, tasks: &[TaskSpec]) -> Result<u64, Error> {
+    let maa = manager.get(id).ok_or(Error::InstanceNotFound)?;
+    if maa.running() || run::current(id).is_some() {
+        manager.stop(id, "interrupted by disconnect")?;
+    }
+    manager.start_tasks(id, tasks)
+}
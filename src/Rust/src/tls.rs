@@ -0,0 +1,61 @@
+use rustls::{
+    server::AllowAnyAuthenticatedClient,
+    version::{TLS12, TLS13},
+    Certificate, PrivateKey, RootCertStore, ServerConfig, SupportedProtocolVersion,
+};
+use std::fs::File;
+use std::io::BufReader;
+use crate::config::Tls;
+
+fn open(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path).map(BufReader::new).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn certs(path: &str) -> Result<Vec<Certificate>, String> {
+    let certs = rustls_pemfile::certs(&mut open(path)?).map_err(|e| format!("{}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("{}: no certificates found", path));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// 返回文件中的第一个私钥
+fn key(path: &str) -> Result<PrivateKey, String> {
+    let mut reader = open(path)?;
+    loop {
+        match rustls_pemfile::read_one(&mut reader).map_err(|e| format!("{}: {}", path, e))? {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => continue,
+            None => return Err(format!("{}: no private key found", path)),
+        }
+    }
+}
+
+/// 读取证书和私钥，设置了client_ca时要求客户端证书
+pub fn server_config(tls: &Tls) -> Result<ServerConfig, String> {
+    let versions: &[&'static SupportedProtocolVersion] = match tls.min_version.as_str() {
+        "1.2" => &[&TLS13, &TLS12],
+        "1.3" => &[&TLS13],
+        version => return Err(format!("unsupported min_version {}", version)),
+    };
+    let builder = ServerConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(versions)
+        .map_err(|e| e.to_string())?;
+    let builder = match &tls.client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in certs(path)? {
+                roots.add(&cert).map_err(|e| format!("{}: {}", path, e))?;
+            }
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots))
+        }
+        None => builder.with_no_client_auth(),
+    };
+    builder
+        .with_single_cert(certs(&tls.cert)?, key(&tls.key)?)
+        .map_err(|e| format!("invalid certificate or key: {}", e))
+}
@@ -0,0 +1,192 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use crate::{
+    api::{MaaManager, MAA_MANAGER},
+    config::{self, Watchdog},
+    database::{msg, run::{self, Run}, writer},
+    maa_sys::AsstMsg,
+    shutdown,
+};
+
+const TICK: Duration = Duration::from_secs(1);
+/// 每个实例保留的触发记录数
+const HISTORY_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trip {
+    pub time: i64,
+    pub run_id: u64,
+    pub reason: String,
+    /// 重新运行时的运行记录id
+    pub restarted_run: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct State {
+    pub last_activity: Option<i64>,
+    /// 最近开始的子任务及其连续开始的次数
+    pub subtask: Option<String>,
+    pub repeats: u32,
+    pub trips: VecDeque<Trip>,
+}
+
+lazy_static! {
+    static ref STATES: Mutex<HashMap<i64, State>> = Mutex::new(HashMap::new());
+    /// 单独设置的实例，其余实例使用配置文件中的设置
+    static ref OVERRIDES: Mutex<HashMap<i64, Watchdog>> = Mutex::new(HashMap::new());
+}
+
+pub fn start() {
+    std::thread::Builder::new()
+        .name("watchdog".to_string())
+        .spawn(|| loop {
+            tick();
+            std::thread::sleep(TICK);
+        })
+        .unwrap();
+}
+
+pub fn settings(instance: i64) -> Watchdog {
+    let overrides = OVERRIDES.lock().unwrap_or_else(PoisonError::into_inner);
+    overrides.get(&instance).cloned().unwrap_or_else(|| config::current().watchdog.clone())
+}
+
+/// 为None时恢复使用配置文件中的设置
+pub fn set(instance: i64, settings: Option<Watchdog>) {
+    let mut overrides = OVERRIDES.lock().unwrap_or_else(PoisonError::into_inner);
+    match settings {
+        Some(settings) => overrides.insert(instance, settings),
+        None => overrides.remove(&instance),
+    };
+}
+
+pub fn get(instance: i64) -> Option<State> {
+    let states = STATES.lock().unwrap_or_else(PoisonError::into_inner);
+    states.get(&instance).cloned()
+}
+
+/// 在写入线程中调用，不能访问MAA_MANAGER
+pub fn on_callback(instance: i64, type_: AsstMsg, value: &Value) {
+    let mut states = STATES.lock().unwrap_or_else(PoisonError::into_inner);
+    let state = states.entry(instance).or_default();
+    state.last_activity = Some(chrono::Local::now().timestamp_millis());
+    match type_ {
+        AsstMsg::TaskChainStart => {
+            state.subtask = None;
+            state.repeats = 0;
+        }
+        AsstMsg::SubTaskStart => {
+            let subtask = value.get("subtask").and_then(Value::as_str).unwrap_or_default();
+            let key = match value.get("details").and_then(|x| x.get("task")).and_then(Value::as_str) {
+                Some(task) => format!("{}:{}", subtask, task),
+                None => subtask.to_string(),
+            };
+            if state.subtask.as_ref() == Some(&key) {
+                state.repeats += 1;
+            } else {
+                state.subtask = Some(key);
+                state.repeats = 1;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 返回需要停止运行的原因
+fn check(settings: &Watchdog, state: &State, run: &Run, now: i64) -> Option<String> {
+    let last = state.last_activity.unwrap_or(run.start).max(run.start);
+    if settings.inactivity_timeout != 0 && now - last > settings.inactivity_timeout as i64 {
+        return Some(format!("no callbacks for {}s", (now - last) / 1000));
+    }
+    if settings.max_duration != 0 && now - run.start > settings.max_duration as i64 {
+        return Some(format!("run exceeded {}s", settings.max_duration / 1000));
+    }
+    if settings.max_repeats != 0 && state.repeats >= settings.max_repeats {
+        return Some(format!(
+            "subtask {} started {} times in a row",
+            state.subtask.as_deref().unwrap_or_default(),
+            state.repeats
+        ));
+    }
+    None
+}
+
+fn tick() {
+    if shutdown::started() {
+        return;
+    }
+    let mut manager = match MAA_MANAGER.lock() {
+        Ok(manager) => manager,
+        Err(_) => return,
+    };
+    let mut states = STATES.lock().unwrap_or_else(PoisonError::into_inner);
+    states.retain(|id, _| manager.get(*id).is_some());
+    let now = chrono::Local::now().timestamp_millis();
+    for id in manager.get_all_id() {
+        let settings = settings(id);
+        if !settings.enabled {
+            continue;
+        }
+        let run = match run::current(id) {
+            Some(run) => run,
+            None => continue,
+        };
+        let state = states.entry(id).or_default();
+        if let Some(reason) = check(&settings, state, &run, now) {
+            let trip = trip(&mut manager, &settings, &run, reason, now);
+            state.last_activity = Some(now);
+            state.subtask = None;
+            state.repeats = 0;
+            if state.trips.len() >= HISTORY_SIZE {
+                state.trips.pop_front();
+            }
+            state.trips.push_back(trip);
+        }
+    }
+}
+
+fn trip(manager: &mut MaaManager, settings: &Watchdog, run: &Run, reason: String, now: i64) -> Trip {
+    let id = run.instance;
+    let _span = tracing::info_span!("watchdog", instance = id, run = run.id).entered();
+    tracing::warn!("watchdog stopped run {} of instance {}: {}", run.id, id, reason);
+    let tasks = run.unfinished();
+    if let Err(e) = manager.stop(id, &format!("watchdog: {}", reason)) {
+        tracing::warn!("watchdog failed to stop instance {}: {:?}", id, e);
+    }
+    if let Some(uuid) = &run.uuid {
+        writer::push(writer::Callback {
+            instance: id,
+            type_: msg::WATCHDOG_TRIPPED,
+            time: now,
+            body: json!({
+                "uuid": uuid,
+                "what": "WatchdogTripped",
+                "why": reason,
+                "details": { "run_id": run.id },
+            })
+            .to_string(),
+        });
+    }
+    if settings.back_to_home {
+        if let Some(Err(e)) = manager.get(id).map(|maa| maa.back_to_home()) {
+            tracing::warn!("watchdog failed to go back to home on instance {}: {:?}", id, e);
+        }
+    }
+    let mut restarted_run = None;
+    if settings.restart && !tasks.is_empty() {
+        match manager.start_tasks(id, &tasks) {
+            Ok(run_id) => restarted_run = Some(run_id),
+            Err(e) => tracing::warn!("watchdog failed to restart instance {}: {:?}", id, e),
+        }
+    }
+    Trip {
+        time: now,
+        run_id: run.id,
+        reason,
+        restarted_run,
+    }
+}